@@ -1,15 +1,171 @@
-use crate::pandata::{Args, Format, FormatOptions};
-use polars::io::avro::{AvroReader, AvroWriter};
-use polars::io::{SerReader, SerWriter};
-use polars::prelude::{IntoLazy, LazyFrame};
-use std::fs::File;
+use crate::avro_container::{Codec, ContainerReader, ContainerWriter};
+use crate::avsc::{self, AvscFile};
+use crate::pandata::{Args, Format, FormatOption, FormatOptions, OptionKind};
+use crate::sink::Sink;
+use crate::source::Source;
+use anyhow::{bail, Context};
+use polars::export::arrow::io::avro::avro_schema::file::Block;
+use polars::export::arrow::io::avro::avro_schema::schema::{Record, Schema};
+use polars::export::arrow::io::avro::{read, write};
+use polars::prelude::{
+    AnyValue, Column, CompatLevel, DataFrame, DataType, IntoLazy, LazyFrame, PlSmallStr, Scalar,
+    SchemaExt,
+};
+use serde_json::Value;
+use std::collections::HashSet;
 
+const CODECS: &[&str] = &["null", "deflate", "snappy", "zstd"];
+
+/// Where a column of the result comes from.
+enum Origin {
+    /// The field of the file with this index.
+    Field(usize),
+    /// A reader schema field missing from the file, filled with its default.
+    Default(Value),
+}
+
+/// An output column: its name, source and, with a schema, its type and
+/// whether it may hold nulls.
+struct Output {
+    name: String,
+    origin: Origin,
+    dtype: Option<(DataType, bool)>,
+}
+
+/// Matches the fields of `reader`, a reader schema, to those of the file by
+/// name or alias, following Avro schema resolution.
+fn resolve(reader: &AvscFile, writer_names: &[&str]) -> anyhow::Result<Vec<Output>> {
+    reader
+        .record
+        .fields
+        .iter()
+        .map(|field| {
+            let index = writer_names.iter().position(|name| {
+                *name == field.name || field.aliases.iter().any(|alias| alias == name)
+            });
+            let origin = match (index, reader.defaults.get(&field.name)) {
+                (Some(index), _) => Origin::Field(index),
+                (None, Some(default)) => Origin::Default(default.clone()),
+                (None, None) => bail!(
+                    "Field {} of the reader schema is not in the file and has no default",
+                    field.name
+                ),
+            };
+            Ok(Output {
+                name: field.name.clone(),
+                origin,
+                dtype: Some(avsc::dtype(&field.schema)?),
+            })
+        })
+        .collect()
+}
+
+fn default_column(name: &str, value: &Value, height: usize) -> anyhow::Result<Column> {
+    let value = match value {
+        Value::Null => AnyValue::Null,
+        Value::Bool(b) => AnyValue::Boolean(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(n) => AnyValue::Int64(n),
+            None => AnyValue::Float64(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::String(s) => AnyValue::StringOwned(s.into()),
+        other => bail!("Unsupported default for field {}: {}", name, other),
+    };
+    let dtype = value.dtype();
+    Ok(Column::new_scalar(
+        name.into(),
+        Scalar::new(dtype, value),
+        height,
+    ))
+}
+
+/// Builds the output columns from `df`, whose columns are named `names`.
+fn output_columns(
+    df: &DataFrame,
+    names: &[&str],
+    outputs: &[Output],
+) -> anyhow::Result<Vec<Column>> {
+    outputs
+        .iter()
+        .map(|output| {
+            let column = match &output.origin {
+                Origin::Field(index) => df.column(names[*index])?.clone(),
+                Origin::Default(value) => default_column(&output.name, value, df.height())?,
+            };
+            let column = match &output.dtype {
+                Some((dtype, nullable)) => {
+                    let column = column.cast(dtype)?;
+                    if !nullable && column.null_count() > 0 {
+                        bail!(
+                            "Field {} is not nullable, but has {} missing values",
+                            output.name,
+                            column.null_count()
+                        );
+                    }
+                    column
+                }
+                None => column,
+            };
+            Ok(column.with_name(output.name.as_str().into()))
+        })
+        .collect()
+}
+
+/// Conforms `df` to the `schema` write option, if given, and returns the
+/// record it is written as.
+fn conform(df: &mut DataFrame, args: &Args) -> anyhow::Result<Record> {
+    let mut record = match args.string("schema") {
+        Some(path) => {
+            let avsc = AvscFile::read(&path)?;
+            let names: Vec<String> = df
+                .get_column_names()
+                .into_iter()
+                .map(|name| name.to_string())
+                .collect();
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+            let outputs = resolve(&avsc, &names)?;
+            for (index, name) in names.iter().enumerate() {
+                let used = outputs
+                    .iter()
+                    .any(|output| matches!(output.origin, Origin::Field(i) if i == index));
+                if !used {
+                    args.log(format!(
+                        "dropping column {}, which is not in the schema",
+                        name
+                    ));
+                }
+            }
+            *df = DataFrame::new(output_columns(df, &names, &outputs)?)?;
+            avsc.record
+        }
+        None => write::to_record(
+            &df.schema().to_arrow(CompatLevel::oldest()),
+            "pandata".to_owned(),
+        )?,
+    };
+    if let Some(name) = args.string("name") {
+        record.name = name;
+    }
+    if let Some(namespace) = args.string("namespace") {
+        record.namespace = Some(namespace);
+    }
+    Ok(record)
+}
+
+#[derive(Default)]
 pub struct AvroFormat;
 
 impl AvroFormat {
     pub fn new() -> Self {
         AvroFormat {}
     }
+
+    /// The schema, as `.avsc` JSON, that `write` would use for `lf`.
+    pub fn schema(&self, lf: LazyFrame, args: &Args) -> anyhow::Result<String> {
+        let mut df = lf.limit(0).collect()?;
+        let record = conform(&mut df, args)?;
+        Ok(serde_json::to_string_pretty(&Schema::Record(record))?)
+    }
 }
 
 impl Format for AvroFormat {
@@ -17,23 +173,143 @@ impl Format for AvroFormat {
         "avro"
     }
 
+    fn sniff(&self, head: &[u8]) -> bool {
+        head.starts_with(b"Obj\x01")
+    }
+
     fn read_options(&self) -> FormatOptions {
         FormatOptions::new()
+            .with(FormatOption::new(
+                "columns",
+                OptionKind::List,
+                "Only read these columns",
+            ))
+            .with(FormatOption::new(
+                "n-rows",
+                OptionKind::Int,
+                "Stop after reading this many rows",
+            ))
+            .with(FormatOption::new(
+                "reader-schema",
+                OptionKind::String,
+                "Path of an .avsc schema to resolve the file against",
+            ))
+    }
+
+    fn write_options(&self) -> FormatOptions {
+        FormatOptions::new()
+            .with(
+                FormatOption::new("name", OptionKind::String, "Name of the top-level record")
+                    .with_default("pandata"),
+            )
+            .with(FormatOption::new(
+                "namespace",
+                OptionKind::String,
+                "Namespace of the top-level record, e.g. com.example",
+            ))
+            .with(
+                FormatOption::new("codec", OptionKind::Enum(CODECS), "Block compression codec")
+                    .with_default("null"),
+            )
+            .with(FormatOption::new(
+                "schema",
+                OptionKind::String,
+                "Path of an .avsc schema setting field names, types and nullability",
+            ))
+    }
+
+    fn reads_streams(&self) -> bool {
+        true
     }
 
-    fn read(&self, path: &str, _args: &Args) -> anyhow::Result<LazyFrame> {
-        let file = File::open(path)?;
-        let df = AvroReader::new(file).finish()?;
+    fn read(&self, source: &Source, args: &Args) -> anyhow::Result<LazyFrame> {
+        let n_rows = match args.int("n-rows")? {
+            Some(n) if n < 0 => bail!("n-rows must not be negative, got: {}", n),
+            n => n.map(|n| n as usize),
+        };
+        let mut reader = ContainerReader::new(source.open()?)?;
+        let schema = read::infer_schema(&reader.record)?;
+        let names: Vec<&str> = schema.iter_names().map(PlSmallStr::as_str).collect();
+
+        let mut outputs = match args.string("reader-schema") {
+            Some(avsc) => resolve(&AvscFile::read(&avsc)?, &names)?,
+            None => (0..names.len())
+                .map(|index| Output {
+                    name: names[index].to_owned(),
+                    origin: Origin::Field(index),
+                    dtype: None,
+                })
+                .collect(),
+        };
+        if let Some(columns) = args.list("columns") {
+            let mut seen = HashSet::new();
+            if let Some(column) = columns.iter().find(|column| !seen.insert(*column)) {
+                bail!("Column listed twice in columns: {}", column);
+            }
+            outputs = columns
+                .iter()
+                .map(|column| {
+                    let index = outputs
+                        .iter()
+                        .position(|output| output.name == *column)
+                        .with_context(|| format!("Column not found: {}", column))?;
+                    Ok(outputs.swap_remove(index))
+                })
+                .collect::<anyhow::Result<_>>()?;
+        }
+
+        // Skip decoding fields no output column reads.
+        let mut projection = vec![false; schema.len()];
+        for output in &outputs {
+            if let Origin::Field(index) = output.origin {
+                projection[index] = true;
+            }
+        }
+        let projected = schema.try_project_indices(
+            &(0..schema.len())
+                .filter(|&index| projection[index])
+                .collect::<Vec<_>>(),
+        )?;
+        let mut df = DataFrame::empty_with_arrow_schema(&projected);
+        let mut height = 0;
+        while n_rows.is_none_or(|n| height < n) {
+            let Some(block) = reader.next_block()? else {
+                break;
+            };
+            let batch = read::deserialize(&block, &schema, &reader.record.fields, &projection)?;
+            df.vstack_mut(&DataFrame::try_from((batch, &projected))?)?;
+            height += block.number_of_rows;
+        }
+        let height = n_rows.map_or(height, |n| height.min(n));
+        let df = df.slice(0, height);
+
+        let mut df = DataFrame::new(output_columns(&df, &names, &outputs)?)?;
+        df.align_chunks_par();
         Ok(df.lazy())
     }
 
-    fn write(&self, path: &str, _args: &Args, lf: LazyFrame) -> anyhow::Result<()> {
-        let mut file = File::create(path)?;
+    fn write(&self, sink: &mut Sink, args: &Args, lf: LazyFrame) -> anyhow::Result<()> {
+        let codec = match args.string("codec").as_deref() {
+            Some("deflate") => Codec::Deflate,
+            Some("snappy") => Codec::Snappy,
+            Some("zstd") => Codec::Zstd,
+            _ => Codec::Null,
+        };
         let mut df = lf.collect()?;
-        df.as_single_chunk_par();
-        AvroWriter::new(&mut file)
-            .with_name("pandata".to_string())
-            .finish(&mut df)?;
-        Ok(())
+        let record = conform(&mut df, args)?;
+        df.align_chunks_par();
+
+        let mut writer = ContainerWriter::new(sink.open()?, record.clone(), codec)?;
+        for chunk in df.iter_chunks(CompatLevel::oldest(), true) {
+            let mut serializers: Vec<_> = chunk
+                .iter()
+                .zip(&record.fields)
+                .map(|(array, field)| write::new_serializer(array.as_ref(), &field.schema))
+                .collect();
+            let mut block = Block::new(chunk.len(), Vec::new());
+            write::serialize(&mut serializers, &mut block);
+            writer.write_block(&block)?;
+        }
+        writer.finish()
     }
 }
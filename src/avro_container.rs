@@ -0,0 +1,256 @@
+//! Reading and writing of Avro object container files. The polars Avro
+//! writer only knows the deflate and snappy codecs and repeats the file
+//! header before every block, so files are framed here and only the record
+//! encoding is left to polars.
+use anyhow::{bail, Context, Result};
+use polars::export::arrow::io::avro::avro_schema::file::Block;
+use polars::export::arrow::io::avro::avro_schema::schema::{Record, Schema};
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{Read, Write};
+
+const MAGIC: &[u8; 4] = b"Obj\x01";
+
+/// The block compression codecs of the Avro specification.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Codec {
+    #[default]
+    Null,
+    Deflate,
+    Snappy,
+    Zstd,
+}
+
+impl Codec {
+    /// The name written to the `avro.codec` header entry.
+    fn name(self) -> &'static str {
+        match self {
+            Codec::Null => "null",
+            Codec::Deflate => "deflate",
+            Codec::Snappy => "snappy",
+            Codec::Zstd => "zstandard",
+        }
+    }
+
+    fn from_name(name: &str) -> Result<Self> {
+        Ok(match name {
+            "null" => Codec::Null,
+            "deflate" => Codec::Deflate,
+            "snappy" => Codec::Snappy,
+            "zstandard" => Codec::Zstd,
+            other => bail!("Unsupported Avro codec: {}", other),
+        })
+    }
+
+    fn compress(self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(match self {
+            Codec::Null => data.to_vec(),
+            Codec::Deflate => {
+                let mut encoder =
+                    flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()?
+            }
+            Codec::Snappy => {
+                let mut compressed = snap::raw::Encoder::new().compress_vec(data)?;
+                compressed.extend(crc32fast::hash(data).to_be_bytes());
+                compressed
+            }
+            Codec::Zstd => zstd::encode_all(data, 0)?,
+        })
+    }
+
+    fn decompress(self, data: Vec<u8>) -> Result<Vec<u8>> {
+        Ok(match self {
+            Codec::Null => data,
+            Codec::Deflate => {
+                let mut decompressed = Vec::new();
+                flate2::read::DeflateDecoder::new(data.as_slice())
+                    .read_to_end(&mut decompressed)?;
+                decompressed
+            }
+            Codec::Snappy => {
+                let Some(split) = data.len().checked_sub(4) else {
+                    bail!("Snappy block is missing its checksum");
+                };
+                let (compressed, crc) = data.split_at(split);
+                let decompressed = snap::raw::Decoder::new().decompress_vec(compressed)?;
+                if crc32fast::hash(&decompressed).to_be_bytes() != crc {
+                    bail!("Snappy block checksum mismatch");
+                }
+                decompressed
+            }
+            Codec::Zstd => zstd::decode_all(data.as_slice())?,
+        })
+    }
+}
+
+fn write_long<W: Write>(writer: &mut W, n: i64) -> Result<()> {
+    let mut z = ((n << 1) ^ (n >> 63)) as u64;
+    while z > 0x7F {
+        writer.write_all(&[(z & 0x7F) as u8 | 0x80])?;
+        z >>= 7;
+    }
+    writer.write_all(&[z as u8])?;
+    Ok(())
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<()> {
+    write_long(writer, bytes.len() as i64)?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+/// Reads a zigzag encoded long, or `None` at a clean end of input.
+fn read_long<R: Read>(reader: &mut R) -> Result<Option<i64>> {
+    let mut z = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0u8];
+        if reader.read(&mut byte)? == 0 {
+            if shift == 0 {
+                return Ok(None);
+            }
+            bail!("Unexpected end of Avro file");
+        }
+        z |= u64::from(byte[0] & 0x7F) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some((z >> 1) as i64 ^ -((z & 1) as i64)));
+        }
+    }
+    bail!("Invalid variable length integer in Avro file")
+}
+
+fn read_length<R: Read>(reader: &mut R) -> Result<usize> {
+    let n = read_long(reader)?.context("Unexpected end of Avro file")?;
+    usize::try_from(n).context("Negative length in Avro file")
+}
+
+fn read_bytes<R: Read>(reader: &mut R) -> Result<Vec<u8>> {
+    let len = read_length(reader)?;
+    let mut bytes = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        bail!("Unexpected end of Avro file");
+    }
+    Ok(bytes)
+}
+
+/// A random sync marker, separating the blocks of one file.
+fn sync_marker() -> [u8; 16] {
+    let state = std::collections::hash_map::RandomState::new();
+    let mut marker = [0u8; 16];
+    for (i, half) in marker.chunks_mut(8).enumerate() {
+        let mut hasher = state.build_hasher();
+        hasher.write_usize(i);
+        half.copy_from_slice(&hasher.finish().to_le_bytes());
+    }
+    marker
+}
+
+/// Writes the header of a container file, then its blocks.
+pub struct ContainerWriter<W: Write> {
+    writer: W,
+    codec: Codec,
+    marker: [u8; 16],
+}
+
+impl<W: Write> ContainerWriter<W> {
+    pub fn new(mut writer: W, record: Record, codec: Codec) -> Result<Self> {
+        let schema = serde_json::to_string(&Schema::Record(record))?;
+        writer.write_all(MAGIC)?;
+        write_long(&mut writer, 2)?;
+        write_bytes(&mut writer, b"avro.schema")?;
+        write_bytes(&mut writer, schema.as_bytes())?;
+        write_bytes(&mut writer, b"avro.codec")?;
+        write_bytes(&mut writer, codec.name().as_bytes())?;
+        write_long(&mut writer, 0)?;
+        let marker = sync_marker();
+        writer.write_all(&marker)?;
+        Ok(Self {
+            writer,
+            codec,
+            marker,
+        })
+    }
+
+    pub fn write_block(&mut self, block: &Block) -> Result<()> {
+        let data = self.codec.compress(&block.data)?;
+        write_long(&mut self.writer, block.number_of_rows as i64)?;
+        write_bytes(&mut self.writer, &data)?;
+        self.writer.write_all(&self.marker)?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads the header of a container file, then its blocks, decompressed.
+pub struct ContainerReader<R: Read> {
+    reader: R,
+    pub record: Record,
+    codec: Codec,
+    marker: [u8; 16],
+}
+
+impl<R: Read> ContainerReader<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic).context("Not an Avro file")?;
+        if &magic != MAGIC {
+            bail!("Not an Avro file");
+        }
+        let mut metadata = HashMap::new();
+        loop {
+            // A negative count is followed by the byte size of the entries.
+            let count = read_long(&mut reader)?.context("Unexpected end of Avro file")?;
+            if count == 0 {
+                break;
+            }
+            if count < 0 {
+                read_long(&mut reader)?;
+            }
+            for _ in 0..count.unsigned_abs() {
+                let key = String::from_utf8(read_bytes(&mut reader)?)?;
+                metadata.insert(key, read_bytes(&mut reader)?);
+            }
+        }
+        let mut marker = [0u8; 16];
+        reader.read_exact(&mut marker)?;
+
+        let schema = metadata
+            .get("avro.schema")
+            .context("Avro file has no schema")?;
+        let record = match serde_json::from_slice(schema).context("Invalid Avro schema")? {
+            Schema::Record(record) => record,
+            _ => bail!("Avro files must hold records"),
+        };
+        let codec = match metadata.get("avro.codec") {
+            Some(name) => Codec::from_name(&String::from_utf8_lossy(name))?,
+            None => Codec::Null,
+        };
+        Ok(Self {
+            reader,
+            record,
+            codec,
+            marker,
+        })
+    }
+
+    /// Reads the next block, or `None` at the end of the file.
+    pub fn next_block(&mut self) -> Result<Option<Block>> {
+        let Some(rows) = read_long(&mut self.reader)? else {
+            return Ok(None);
+        };
+        let data = self.codec.decompress(read_bytes(&mut self.reader)?)?;
+        let mut marker = [0u8; 16];
+        self.reader.read_exact(&mut marker)?;
+        if marker != self.marker {
+            bail!("Avro block sync marker mismatch");
+        }
+        let rows = usize::try_from(rows).context("Negative row count in Avro file")?;
+        Ok(Some(Block::new(rows, data)))
+    }
+}
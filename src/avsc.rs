@@ -0,0 +1,107 @@
+//! Avro schema files, `.avsc`.
+use anyhow::{bail, Context, Result};
+use polars::export::arrow::io::avro::avro_schema::schema::{
+    BytesLogical, IntLogical, LongLogical, Record, Schema,
+};
+use polars::prelude::{DataType, Field, TimeUnit};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// A record schema read from an `.avsc` file, with the default value of each
+/// top-level field that declares one.
+pub struct AvscFile {
+    pub record: Record,
+    pub defaults: HashMap<String, Value>,
+}
+
+impl AvscFile {
+    pub fn read(path: &str) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Unable to read Avro schema: {}", path))?;
+        Self::parse(&text).with_context(|| format!("Invalid Avro schema: {}", path))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut json: Value = serde_json::from_str(text)?;
+        let mut defaults = HashMap::new();
+        if let Some(fields) = json.get_mut("fields").and_then(Value::as_array_mut) {
+            for field in fields {
+                let name = field.get("name").and_then(Value::as_str).map(str::to_owned);
+                if let (Some(name), Some(default)) = (name, take_default(field)) {
+                    defaults.insert(name, default);
+                }
+            }
+        }
+        // The schema parser reads field defaults as schemas, so values such
+        // as `0` are rejected; they are kept aside instead.
+        strip_defaults(&mut json);
+        match serde_json::from_value(json)? {
+            Schema::Record(record) => Ok(Self { record, defaults }),
+            _ => bail!("The top-level schema must be a record"),
+        }
+    }
+}
+
+fn take_default(field: &mut Value) -> Option<Value> {
+    field.as_object_mut()?.remove("default")
+}
+
+fn strip_defaults(json: &mut Value) {
+    match json {
+        Value::Object(map) => {
+            if let Some(Value::Array(fields)) = map.get_mut("fields") {
+                for field in fields.iter_mut() {
+                    take_default(field);
+                }
+            }
+            map.values_mut().for_each(strip_defaults);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_defaults),
+        _ => {}
+    }
+}
+
+/// The polars type holding values of `schema`, and whether it is nullable.
+pub fn dtype(schema: &Schema) -> Result<(DataType, bool)> {
+    let dtype = match schema {
+        Schema::Boolean => DataType::Boolean,
+        Schema::Int(None) => DataType::Int32,
+        Schema::Int(Some(IntLogical::Date)) => DataType::Date,
+        Schema::Long(None) => DataType::Int64,
+        Schema::Long(Some(LongLogical::TimestampMillis | LongLogical::LocalTimestampMillis)) => {
+            DataType::Datetime(TimeUnit::Milliseconds, None)
+        }
+        Schema::Long(Some(LongLogical::TimestampMicros | LongLogical::LocalTimestampMicros)) => {
+            DataType::Datetime(TimeUnit::Microseconds, None)
+        }
+        Schema::Float => DataType::Float32,
+        Schema::Double => DataType::Float64,
+        Schema::Bytes(None) => DataType::Binary,
+        Schema::Bytes(Some(BytesLogical::Decimal(precision, scale))) => {
+            DataType::Decimal(Some(*precision), Some(*scale))
+        }
+        Schema::String(_) => DataType::String,
+        Schema::Array(item) => DataType::List(Box::new(dtype(item)?.0)),
+        Schema::Record(record) => DataType::Struct(
+            record
+                .fields
+                .iter()
+                .map(|field| {
+                    Ok(Field::new(
+                        field.name.as_str().into(),
+                        dtype(&field.schema)?.0,
+                    ))
+                })
+                .collect::<Result<_>>()?,
+        ),
+        Schema::Union(variants) if variants.len() == 2 && variants.contains(&Schema::Null) => {
+            let inner = variants
+                .iter()
+                .find(|v| **v != Schema::Null)
+                .unwrap_or(&Schema::Null);
+            return Ok((dtype(inner)?.0, true));
+        }
+        other => bail!("Unsupported Avro type: {}", serde_json::to_string(other)?),
+    };
+    Ok((dtype, false))
+}
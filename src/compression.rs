@@ -0,0 +1,192 @@
+//! Whole-file compression wrapped around another format, e.g. `data.csv.gz`.
+use anyhow::{bail, Result};
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// The compression of a file as a whole.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Uncompressed,
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+    Lz4,
+}
+
+impl Compression {
+    pub const NAMES: &'static [&'static str] =
+        &["uncompressed", "gzip", "zstd", "bzip2", "xz", "lz4"];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::Uncompressed => "uncompressed",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Bzip2 => "bzip2",
+            Compression::Xz => "xz",
+            Compression::Lz4 => "lz4",
+        }
+    }
+
+    /// Looks up a compression by name or file extension.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "uncompressed" | "none" => Compression::Uncompressed,
+            "gzip" | "gz" => Compression::Gzip,
+            "zstd" | "zst" => Compression::Zstd,
+            "bzip2" | "bz2" => Compression::Bzip2,
+            "xz" => Compression::Xz,
+            "lz4" => Compression::Lz4,
+            _ => return None,
+        })
+    }
+
+    /// The compression named by the last extension of `path`, if any.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let ext = path.as_ref().extension()?.to_str()?;
+        Self::from_name(ext).filter(|c| *c != Compression::Uncompressed)
+    }
+
+    /// Detects a compression from the magic bytes at the start of a file.
+    pub fn sniff(head: &[u8]) -> Option<Self> {
+        if head.starts_with(&[0x1f, 0x8b]) {
+            Some(Compression::Gzip)
+        } else if head.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Compression::Zstd)
+        } else if head.len() >= 10
+            && head.starts_with(b"BZh")
+            && head[3].is_ascii_digit()
+            && head[4..10] == [0x31, 0x41, 0x59, 0x26, 0x53, 0x59]
+        {
+            Some(Compression::Bzip2)
+        } else if head.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Compression::Xz)
+        } else if head.starts_with(&[0x04, 0x22, 0x4d, 0x18]) {
+            Some(Compression::Lz4)
+        } else {
+            None
+        }
+    }
+
+    /// The levels the codec accepts.
+    fn levels(self) -> (i32, i32) {
+        match self {
+            Compression::Uncompressed => (0, 0),
+            Compression::Gzip | Compression::Xz => (0, 9),
+            Compression::Zstd => (1, 22),
+            Compression::Bzip2 => (1, 9),
+            Compression::Lz4 => (0, 16),
+        }
+    }
+
+    /// Checks that the codec accepts `level`.
+    pub fn check_level(self, level: i32) -> Result<()> {
+        if self == Compression::Uncompressed {
+            bail!("A compression level needs a compression");
+        }
+        let (min, max) = self.levels();
+        if !(min..=max).contains(&level) {
+            bail!(
+                "{} compression level must be between {} and {}, got: {}",
+                self.name(),
+                min,
+                max,
+                level
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "compression")]
+impl Compression {
+    /// Wraps `reader` to decompress it.
+    pub fn decoder(self, reader: Box<dyn Read + Send>) -> Result<Box<dyn Read + Send>> {
+        Ok(match self {
+            Compression::Uncompressed => reader,
+            Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+            Compression::Zstd => Box::new(zstd::Decoder::new(reader)?),
+            Compression::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(reader)),
+            Compression::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(reader)),
+            Compression::Lz4 => Box::new(lz4::Decoder::new(reader)?),
+        })
+    }
+
+    /// Compresses all of `reader` into `writer`, at the codec's default
+    /// level unless one is given.
+    pub fn compress(
+        self,
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+        level: Option<i32>,
+    ) -> Result<()> {
+        if let Some(level) = level {
+            self.check_level(level)?;
+        }
+        match self {
+            Compression::Uncompressed => {
+                std::io::copy(reader, writer)?;
+            }
+            Compression::Gzip => {
+                let level = level.map_or(flate2::Compression::default(), |l| {
+                    flate2::Compression::new(l as u32)
+                });
+                let mut encoder = flate2::write::GzEncoder::new(writer, level);
+                std::io::copy(reader, &mut encoder)?;
+                encoder.finish()?;
+            }
+            Compression::Zstd => {
+                let mut encoder = zstd::Encoder::new(writer, level.unwrap_or(0))?;
+                std::io::copy(reader, &mut encoder)?;
+                encoder.finish()?;
+            }
+            Compression::Bzip2 => {
+                let level = level.map_or(bzip2::Compression::default(), |l| {
+                    bzip2::Compression::new(l as u32)
+                });
+                let mut encoder = bzip2::write::BzEncoder::new(writer, level);
+                std::io::copy(reader, &mut encoder)?;
+                encoder.finish()?;
+            }
+            Compression::Xz => {
+                let mut encoder = xz2::write::XzEncoder::new(writer, level.unwrap_or(6) as u32);
+                std::io::copy(reader, &mut encoder)?;
+                encoder.finish()?;
+            }
+            Compression::Lz4 => {
+                let mut encoder = lz4::EncoderBuilder::new()
+                    .level(level.unwrap_or(0) as u32)
+                    .build(writer)?;
+                std::io::copy(reader, &mut encoder)?;
+                encoder.finish().1?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "compression"))]
+impl Compression {
+    pub fn decoder(self, reader: Box<dyn Read + Send>) -> Result<Box<dyn Read + Send>> {
+        match self {
+            Compression::Uncompressed => Ok(reader),
+            _ => bail!("{} compression is not supported by this build", self.name()),
+        }
+    }
+
+    pub fn compress(
+        self,
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+        level: Option<i32>,
+    ) -> Result<()> {
+        match (self, level) {
+            (Compression::Uncompressed, None) => {
+                std::io::copy(reader, writer)?;
+                Ok(())
+            }
+            _ => bail!("{} compression is not supported by this build", self.name()),
+        }
+    }
+}
@@ -1,8 +1,176 @@
-use crate::pandata::{Args, Format, FormatOptions};
+use crate::dialect::Dialect;
+use crate::pandata::{Args, Format, FormatOption, FormatOptions, OptionKind};
+use crate::sink::Sink;
+use crate::source::Source;
 use polars::io::SerReader;
-use polars::prelude::{CsvParseOptions, CsvReadOptions, CsvWriterOptions, IntoLazy, LazyFrame};
+use polars::prelude::{
+    CsvParseOptions, CsvReadOptions, CsvWriterOptions, IntoLazy, LazyCsvReader, LazyFileListReader,
+    LazyFrame, QuoteStyle,
+};
+use std::io::Cursor;
 use std::path::PathBuf;
 
+const QUOTE_STYLES: &[&str] = &["necessary", "always", "non-numeric", "never"];
+
+/// Write options shared by the CSV and TSV writers.
+pub(crate) fn csv_write_options(default_separator: &'static str) -> FormatOptions {
+    FormatOptions::new()
+        .with(
+            FormatOption::new("separator", OptionKind::Char, "Field delimiter")
+                .with_default(default_separator),
+        )
+        .with(
+            FormatOption::new("quote-char", OptionKind::Char, "Quote character").with_default("\""),
+        )
+        .with(
+            FormatOption::new(
+                "quote-style",
+                OptionKind::Enum(QUOTE_STYLES),
+                "When to quote fields",
+            )
+            .with_default("necessary"),
+        )
+        .with(
+            FormatOption::new("header", OptionKind::Bool, "Write a header row")
+                .with_default("true"),
+        )
+        .with(
+            FormatOption::new(
+                "line-terminator",
+                OptionKind::String,
+                "String ending each row",
+            )
+            .with_default("\\n"),
+        )
+        .with(FormatOption::new(
+            "null-value",
+            OptionKind::String,
+            "String written for missing values",
+        ))
+        .with(FormatOption::new(
+            "date-format",
+            OptionKind::String,
+            "chrono format string for dates",
+        ))
+        .with(FormatOption::new(
+            "datetime-format",
+            OptionKind::String,
+            "chrono format string for datetimes",
+        ))
+        .with(FormatOption::new(
+            "time-format",
+            OptionKind::String,
+            "chrono format string for times",
+        ))
+        .with(FormatOption::new(
+            "float-precision",
+            OptionKind::Int,
+            "Number of decimal places for floats",
+        ))
+        .with(FormatOption::new(
+            "float-scientific",
+            OptionKind::Bool,
+            "Write floats in scientific notation",
+        ))
+}
+
+/// Builds `CsvWriterOptions` from the options declared by `csv_write_options`.
+pub(crate) fn csv_writer_options(args: &Args, separator: u8) -> anyhow::Result<CsvWriterOptions> {
+    let mut options = CsvWriterOptions {
+        maintain_order: true,
+        ..Default::default()
+    };
+    let serialize = &mut options.serialize_options;
+    serialize.separator = args.char("separator")?.unwrap_or(separator);
+    if let Some(quote_char) = args.char("quote-char")? {
+        serialize.quote_char = quote_char;
+    }
+    if let Some(style) = args.string("quote-style") {
+        serialize.quote_style = match style.as_str() {
+            "always" => QuoteStyle::Always,
+            "non-numeric" => QuoteStyle::NonNumeric,
+            "never" => QuoteStyle::Never,
+            _ => QuoteStyle::Necessary,
+        };
+    }
+    if let Some(terminator) = args.string("line-terminator") {
+        serialize.line_terminator = unescape(&terminator);
+    }
+    if let Some(null) = args.string("null-value") {
+        serialize.null = null;
+    }
+    serialize.date_format = args.string("date-format");
+    serialize.datetime_format = args.string("datetime-format");
+    serialize.time_format = args.string("time-format");
+    serialize.float_scientific = args.bool("float-scientific")?;
+    if let Some(precision) = args.int("float-precision")? {
+        let precision = usize::try_from(precision)
+            .map_err(|_| anyhow::anyhow!("float-precision must not be negative"))?;
+        serialize.float_precision = Some(precision);
+    }
+    if let Some(header) = args.bool("header")? {
+        options.include_header = header;
+    }
+    Ok(options)
+}
+
+/// Sniffs the dialect of a file or of in-memory data.
+pub(crate) fn sniff_dialect(source: &Source) -> anyhow::Result<Option<Dialect>> {
+    match (source, source.path()) {
+        (Source::Bytes(bytes), _) => Ok(Some(Dialect::sniff_head(bytes))),
+        (_, Some(path)) => Dialect::sniff_path(path),
+        _ => Ok(None),
+    }
+}
+
+/// Reads delimited data, lazily for files so that sinks can stream them. The
+/// scanner memory-maps its input, which fails with "No such device" for pipes
+/// such as `/dev/stdin`, so those are read eagerly instead.
+pub(crate) fn read_delimited(
+    source: &Source,
+    format: &str,
+    dialect: Dialect,
+    args: &Args,
+) -> anyhow::Result<LazyFrame> {
+    let parse_options = CsvParseOptions::default()
+        .with_separator(dialect.separator)
+        .with_quote_char(Some(dialect.quote_char));
+    let read_options = CsvReadOptions::default()
+        .with_has_header(dialect.has_header)
+        .with_parse_options(parse_options);
+    if let Source::Bytes(bytes) = source {
+        let df = read_options
+            .into_reader_with_file_handle(Cursor::new(bytes.clone()))
+            .finish()?;
+        return Ok(df.lazy());
+    }
+    let path = source.require_path(format)?;
+    if !std::fs::metadata(path).is_ok_and(|m| m.is_file()) {
+        args.log(format!(
+            "{} is not a regular file, reading it into memory",
+            path
+        ));
+        let df = read_options
+            .try_into_reader_with_file_path(Some(PathBuf::from(path)))?
+            .finish()?;
+        return Ok(df.lazy());
+    }
+    let lf = LazyCsvReader::new(path)
+        .with_separator(dialect.separator)
+        .with_quote_char(Some(dialect.quote_char))
+        .with_has_header(dialect.has_header)
+        .finish()?;
+    Ok(lf)
+}
+
+/// Expands `\n`, `\r` and `\t`, which are awkward to pass on a command line.
+fn unescape(s: &str) -> String {
+    s.replace("\\n", "\n")
+        .replace("\\r", "\r")
+        .replace("\\t", "\t")
+}
+
+#[derive(Default)]
 pub struct CsvFormat;
 
 impl CsvFormat {
@@ -16,35 +184,64 @@ impl Format for CsvFormat {
         "csv"
     }
 
-    fn read_options(&self) -> FormatOptions {
-        FormatOptions::from_keys(["separator", "quote-char"])
+    fn extensions(&self) -> Vec<&'static str> {
+        vec!["csv", "txt"]
     }
 
-    fn read(&self, path: &str, args: &Args) -> anyhow::Result<LazyFrame> {
-        // TODO: this crashes
-        // let lf = LazyCsvReader::new(path)
-        //     .with_has_header(true)
-        //     .finish()?;
+    fn supports_nested(&self) -> bool {
+        false
+    }
 
-        let mut parse_options = CsvParseOptions::default();
-        if let Some(sep) = args.char("separator") {
-            parse_options = parse_options.with_separator(sep)
-        }
-        if let Some(quote_char) = args.char("quote-char") {
-            parse_options = parse_options.with_quote_char(Some(quote_char))
+    fn read_options(&self) -> FormatOptions {
+        FormatOptions::new()
+            .with(
+                FormatOption::new("separator", OptionKind::Char, "Field delimiter")
+                    .with_default(","),
+            )
+            .with(
+                FormatOption::new("quote-char", OptionKind::Char, "Quote character")
+                    .with_default("\""),
+            )
+            .with(FormatOption::new(
+                "has-header",
+                OptionKind::Bool,
+                "Whether the first row holds column names",
+            ))
+            .with(
+                FormatOption::new(
+                    "sniff",
+                    OptionKind::Bool,
+                    "Detect the separator, quote character and header from the data",
+                )
+                .with_default("true"),
+            )
+    }
+
+    fn read(&self, source: &Source, args: &Args) -> anyhow::Result<LazyFrame> {
+        let mut dialect = Dialect::default();
+        if args.bool("sniff")?.unwrap_or(true) {
+            if let Some(sniffed) = sniff_dialect(source)? {
+                args.log(format!(
+                    "sniffed csv dialect: separator {:?}, quote-char {:?}, has-header {}",
+                    sniffed.separator as char, sniffed.quote_char as char, sniffed.has_header
+                ));
+                dialect = sniffed;
+            }
         }
-        let read_options = CsvReadOptions::default().with_parse_options(parse_options);
-        let lf = read_options
-            .try_into_reader_with_file_path(Some(PathBuf::from(path)))?
-            .finish()?
-            .lazy();
-        Ok(lf)
+        let dialect = Dialect {
+            separator: args.char("separator")?.unwrap_or(dialect.separator),
+            quote_char: args.char("quote-char")?.unwrap_or(dialect.quote_char),
+            has_header: args.bool("has-header")?.unwrap_or(dialect.has_header),
+        };
+        read_delimited(source, "csv", dialect, args)
+    }
+
+    fn write_options(&self) -> FormatOptions {
+        csv_write_options(",")
     }
 
-    fn write(&self, path: &str, _args: &Args, lf: LazyFrame) -> anyhow::Result<()> {
-        let mut options = CsvWriterOptions::default();
-        options.maintain_order = true;
-        lf.sink_csv(path, options)?;
-        Ok(())
+    fn write(&self, sink: &mut Sink, args: &Args, lf: LazyFrame) -> anyhow::Result<()> {
+        let options = csv_writer_options(args, b',')?;
+        sink.with_path(|path| Ok(lf.sink_csv(path, options)?))
     }
 }
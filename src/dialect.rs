@@ -0,0 +1,203 @@
+use anyhow::Result;
+use std::fs::File;
+use std::io::Read;
+
+/// Separators tried when sniffing, in order of preference on ties.
+const SEPARATORS: &[u8] = b",;|\t";
+const QUOTES: &[u8] = b"\"'";
+const SAMPLE_LEN: usize = 64 * 1024;
+const MAX_RECORDS: usize = 200;
+
+/// The separator, quote character and header row of a delimited text file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Dialect {
+    pub separator: u8,
+    pub quote_char: u8,
+    pub has_header: bool,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self {
+            separator: b',',
+            quote_char: b'"',
+            has_header: true,
+        }
+    }
+}
+
+impl Dialect {
+    /// Guesses the dialect of `sample`, which should hold whole lines.
+    pub fn sniff(sample: &[u8]) -> Self {
+        let quote_char = sniff_quote(sample);
+        let separator = sniff_separator(sample, quote_char);
+        let has_header = sniff_header(&records(sample, separator, quote_char));
+        Self {
+            separator,
+            quote_char,
+            has_header,
+        }
+    }
+
+    /// Sniffs the start of the file at `path`. Returns `None` for paths that
+    /// are not regular files, since reading a sample from a pipe would
+    /// consume it.
+    pub fn sniff_path(path: &str) -> Result<Option<Self>> {
+        if !std::fs::metadata(path).is_ok_and(|m| m.is_file()) {
+            return Ok(None);
+        }
+        let mut sample = Vec::new();
+        File::open(path)?
+            .take(SAMPLE_LEN as u64)
+            .read_to_end(&mut sample)?;
+        Ok(Some(Self::sniff_head(&sample)))
+    }
+
+    /// Sniffs the start of `data`, which may end mid-line.
+    pub fn sniff_head(data: &[u8]) -> Self {
+        let mut sample = &data[..data.len().min(SAMPLE_LEN)];
+        if sample.len() == SAMPLE_LEN {
+            // Drop the partial last line.
+            if let Some(end) = sample.iter().rposition(|b| *b == b'\n') {
+                sample = &sample[..end + 1];
+            }
+        }
+        Self::sniff(sample)
+    }
+}
+
+/// Picks the quote character that most often opens or closes a field.
+fn sniff_quote(sample: &[u8]) -> u8 {
+    let is_boundary = |b: Option<&u8>| match b {
+        None => true,
+        Some(b) => *b == b'\n' || *b == b'\r' || SEPARATORS.contains(b),
+    };
+    let score = |quote: u8| {
+        (0..sample.len())
+            .filter(|&i| sample[i] == quote)
+            .filter(|&i| {
+                let before = i.checked_sub(1).map(|j| &sample[j]);
+                is_boundary(before) || is_boundary(sample.get(i + 1))
+            })
+            .count()
+    };
+    QUOTES
+        .iter()
+        .copied()
+        .map(|quote| (score(quote), quote))
+        .filter(|(score, _)| *score > 0)
+        .max_by_key(|(score, quote)| (*score, std::cmp::Reverse(*quote == b'\'')))
+        .map_or(b'"', |(_, quote)| quote)
+}
+
+/// Picks the separator that splits records into the most consistent number
+/// of fields.
+fn sniff_separator(sample: &[u8], quote_char: u8) -> u8 {
+    let mut best: Option<(usize, usize, u8)> = None;
+    for &separator in SEPARATORS {
+        let counts: Vec<usize> = records(sample, separator, quote_char)
+            .iter()
+            .map(Vec::len)
+            .collect();
+        let mut modes: Vec<(usize, usize)> = Vec::new();
+        for &count in &counts {
+            match modes.iter_mut().find(|(c, _)| *c == count) {
+                Some((_, n)) => *n += 1,
+                None => modes.push((count, 1)),
+            }
+        }
+        let Some(&(fields, frequency)) = modes.iter().max_by_key(|(c, n)| (*n, *c)) else {
+            continue;
+        };
+        if fields < 2 {
+            continue;
+        }
+        let better = match best {
+            None => true,
+            Some((best_frequency, best_fields, _)) => {
+                (frequency, fields) > (best_frequency, best_fields)
+            }
+        };
+        if better {
+            best = Some((frequency, fields, separator));
+        }
+    }
+    best.map_or(b',', |(_, _, separator)| separator)
+}
+
+/// Votes on whether the first record is a header, by comparing each of its
+/// fields with the rest of the column: a text field above numbers, or a field
+/// of a different length above fixed-length values, suggests a header.
+/// Ambiguous samples are assumed to have one.
+fn sniff_header(records: &[Vec<String>]) -> bool {
+    let Some((header, rows)) = records.split_first() else {
+        return true;
+    };
+    let is_number = |s: &str| s.trim().parse::<f64>().is_ok();
+    let mut votes = 0i32;
+    for (idx, name) in header.iter().enumerate() {
+        let values: Vec<&str> = rows
+            .iter()
+            .filter_map(|row| row.get(idx))
+            .map(String::as_str)
+            .filter(|v| !v.is_empty())
+            .collect();
+        if values.is_empty() {
+            continue;
+        }
+        if values.iter().all(|v| is_number(v)) {
+            votes += if is_number(name) { -1 } else { 1 };
+            continue;
+        }
+        let len = values[0].chars().count();
+        if values.iter().all(|v| v.chars().count() == len) {
+            votes += if name.chars().count() == len { -1 } else { 1 };
+        }
+    }
+    votes >= 0
+}
+
+/// Splits `sample` into records of unquoted fields.
+fn records(sample: &[u8], separator: u8, quote_char: u8) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = Vec::new();
+    let mut in_quotes = false;
+    let mut bytes = sample.iter().copied().peekable();
+    while let Some(b) = bytes.next() {
+        if in_quotes {
+            if b == quote_char {
+                if bytes.peek() == Some(&quote_char) {
+                    field.push(b);
+                    bytes.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(b);
+            }
+        } else if b == quote_char {
+            in_quotes = true;
+        } else if b == separator {
+            record.push(String::from_utf8_lossy(&field).into_owned());
+            field.clear();
+        } else if b == b'\n' {
+            if field.last() == Some(&b'\r') {
+                field.pop();
+            }
+            record.push(String::from_utf8_lossy(&field).into_owned());
+            field.clear();
+            records.push(std::mem::take(&mut record));
+            if records.len() == MAX_RECORDS {
+                return records;
+            }
+        } else {
+            field.push(b);
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(String::from_utf8_lossy(&field).into_owned());
+        records.push(record);
+    }
+    records
+}
@@ -0,0 +1,520 @@
+use crate::pandata::{Args, Format, FormatOption, FormatOptions, OptionKind};
+use crate::sink::Sink;
+use crate::source::Source;
+use anyhow::{bail, Context};
+use calamine::{
+    open_workbook_auto, open_workbook_auto_from_rs, Data, DataType as _, Range, Reader, Sheets,
+};
+use polars::prelude::{
+    Column, DataFrame, DataType, IntoLazy, LazyFrame, NamedFrom, Series, TimeUnit,
+};
+use rust_xlsxwriter::{Format as CellFormat, Workbook, Worksheet};
+use std::collections::HashSet;
+use std::io::{Cursor, Read, Seek, Write};
+use std::path::Path;
+
+const CFB_MAGIC: &[u8] = &[0xd0, 0xcf, 0x11, 0xe0, 0xa1, 0xb1, 0x1a, 0xe1];
+const ODS_MIMETYPE: &[u8] = b"mimetypeapplication/vnd.oasis.opendocument.spreadsheet";
+
+/// The rows of a worksheet, including the header.
+const MAX_ROWS: usize = 1_048_576;
+const MAX_COLUMNS: usize = 16_384;
+/// Days from Excel's epoch, 1899-12-30, to the Unix epoch.
+const UNIX_EPOCH_SERIAL: f64 = 25_569.0;
+const MS_PER_DAY: f64 = 86_400_000.0;
+
+#[derive(Default)]
+pub struct ExcelFormat;
+
+impl ExcelFormat {
+    pub fn new() -> Self {
+        ExcelFormat {}
+    }
+}
+
+/// Parses a cell reference such as `B3` into a zero-based (row, column).
+fn parse_cell(cell: &str) -> anyhow::Result<(u32, u32)> {
+    let split = cell
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(cell.len());
+    let (letters, digits) = cell.split_at(split);
+    let invalid = || anyhow::anyhow!("Invalid cell reference: {}", cell);
+    let row: u32 = digits.parse().unwrap_or(0);
+    if letters.is_empty() || row == 0 || row as usize > MAX_ROWS {
+        return Err(invalid());
+    }
+    let col = letters.bytes().try_fold(0u32, |col, b| {
+        col.checked_mul(26)?
+            .checked_add(u32::from(b.to_ascii_uppercase() - b'A' + 1))
+            .filter(|&col| col as usize <= MAX_COLUMNS)
+    });
+    let col = col.ok_or_else(invalid)?;
+    Ok((row - 1, col - 1))
+}
+
+/// Selects the cells of `spec`, e.g. `A1:D20`, or from a start cell, e.g.
+/// `B3`, to the end of the used range.
+fn select_range(range: &Range<Data>, spec: &str) -> anyhow::Result<Range<Data>> {
+    let (start, end) = match spec.split_once(':') {
+        Some((start, end)) => (parse_cell(start)?, Some(parse_cell(end)?)),
+        None => (parse_cell(spec)?, None),
+    };
+    let end = match end.or(range.end()) {
+        Some(end) => end,
+        None => return Ok(Range::empty()),
+    };
+    if end.0 < start.0 || end.1 < start.1 {
+        bail!("Invalid cell range: {}", spec);
+    }
+    Ok(range.range(start, end))
+}
+
+fn sheet_range<RS: Read + Seek>(
+    workbook: &mut Sheets<RS>,
+    sheet: Option<&str>,
+) -> anyhow::Result<Range<Data>> {
+    let names = workbook.sheet_names();
+    let name = match sheet {
+        None => names.first().context("The workbook has no sheets")?.clone(),
+        Some(name) if names.iter().any(|n| n == name) => name.to_owned(),
+        Some(index) => match index.parse::<usize>().ok().and_then(|i| names.get(i)) {
+            Some(name) => name.clone(),
+            None => bail!(
+                "Sheet not found: {}; the workbook has {}",
+                index,
+                names.join(", ")
+            ),
+        },
+    };
+    Ok(workbook.worksheet_range(&name)?)
+}
+
+/// The type of the values in a column.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Empty,
+    Bool,
+    Int,
+    Float,
+    Date,
+    Datetime,
+    Duration,
+    String,
+}
+
+impl Kind {
+    fn of(cell: &Data) -> Kind {
+        match cell {
+            Data::Empty | Data::Error(_) => Kind::Empty,
+            Data::Bool(_) => Kind::Bool,
+            Data::Int(_) => Kind::Int,
+            // Excel stores all numbers as floats.
+            Data::Float(f) if f.fract() == 0.0 && f.abs() < 2f64.powi(53) => Kind::Int,
+            Data::Float(_) => Kind::Float,
+            Data::DateTime(dt) if dt.is_duration() => Kind::Duration,
+            Data::DateTime(dt) if dt.as_f64().fract() == 0.0 => Kind::Date,
+            Data::DateTime(_) => Kind::Datetime,
+            Data::DateTimeIso(s) if s.contains('T') => Kind::Datetime,
+            Data::DateTimeIso(_) => Kind::Date,
+            Data::DurationIso(_) => Kind::Duration,
+            Data::String(_) => Kind::String,
+        }
+    }
+
+    /// The kind holding values of both kinds; strings if nothing narrower.
+    fn merge(self, other: Kind) -> Kind {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Kind::Empty, k) | (k, Kind::Empty) => k,
+            (Kind::Int, Kind::Float) | (Kind::Float, Kind::Int) => Kind::Float,
+            (Kind::Date, Kind::Datetime) | (Kind::Datetime, Kind::Date) => Kind::Datetime,
+            _ => Kind::String,
+        }
+    }
+}
+
+fn cell_string(cell: &Data) -> Option<String> {
+    match cell {
+        Data::Empty | Data::Error(_) => None,
+        Data::DateTime(dt) if dt.is_duration() => cell.as_duration().map(|d| d.to_string()),
+        Data::DateTime(_) => cell.as_datetime().map(|dt| dt.to_string()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// Builds a column from cells, typed by the values they hold.
+fn column(name: &str, cells: &[&Data]) -> anyhow::Result<Column> {
+    let kind = cells
+        .iter()
+        .fold(Kind::Empty, |kind, cell| kind.merge(Kind::of(cell)));
+    let name = name.into();
+    let series = match kind {
+        Kind::Bool => {
+            let values: Vec<Option<bool>> = cells.iter().map(|c| c.get_bool()).collect();
+            Series::new(name, values)
+        }
+        Kind::Int => {
+            let values: Vec<Option<i64>> = cells.iter().map(|c| c.as_i64()).collect();
+            Series::new(name, values)
+        }
+        Kind::Float => {
+            let values: Vec<Option<f64>> = cells
+                .iter()
+                .map(|c| c.as_f64().filter(|_| !c.is_empty() && !c.is_error()))
+                .collect();
+            Series::new(name, values)
+        }
+        Kind::Date => {
+            let days: Vec<Option<i32>> = cells
+                .iter()
+                .map(|c| {
+                    let midnight = c.as_date()?.and_hms_opt(0, 0, 0)?;
+                    Some(midnight.and_utc().timestamp().div_euclid(86_400) as i32)
+                })
+                .collect();
+            Series::new(name, days).cast(&DataType::Date)?
+        }
+        Kind::Datetime => {
+            let millis: Vec<Option<i64>> = cells
+                .iter()
+                .map(|c| Some(c.as_datetime()?.and_utc().timestamp_millis()))
+                .collect();
+            Series::new(name, millis).cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?
+        }
+        Kind::Duration => {
+            let millis: Vec<Option<i64>> = cells
+                .iter()
+                .map(|c| Some(c.as_duration()?.num_milliseconds()))
+                .collect();
+            Series::new(name, millis).cast(&DataType::Duration(TimeUnit::Milliseconds))?
+        }
+        Kind::Empty | Kind::String => {
+            let values: Vec<Option<String>> = cells.iter().map(|c| cell_string(c)).collect();
+            Series::new(name, values)
+        }
+    };
+    Ok(series.into())
+}
+
+/// Builds a frame from `range`, whose row `header_row` holds the column
+/// names unless `has_header` is false. Rows above the header are skipped.
+fn frame(range: &Range<Data>, header_row: usize, has_header: bool) -> anyhow::Result<DataFrame> {
+    if header_row > 0 && header_row >= range.height() {
+        bail!(
+            "header-row {} is beyond the {} rows of the range",
+            header_row,
+            range.height()
+        );
+    }
+    let rows: Vec<&[Data]> = range.rows().skip(header_row).collect();
+    let (header, rows) = match rows.split_first() {
+        Some((header, rows)) if has_header => (Some(*header), rows),
+        _ => (None, rows.as_slice()),
+    };
+    let mut seen = HashSet::new();
+    let columns = (0..range.width())
+        .map(|index| {
+            let mut name = header
+                .and_then(|header| cell_string(&header[index]))
+                .unwrap_or_else(|| format!("column_{}", index + 1));
+            if !seen.insert(name.clone()) {
+                name = format!("{}_duplicated_{}", name, index);
+                seen.insert(name.clone());
+            }
+            let cells: Vec<&Data> = rows.iter().map(|row| &row[index]).collect();
+            column(&name, &cells)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(DataFrame::new(columns)?)
+}
+
+/// Number formats of the cells written for temporal columns.
+struct CellFormats {
+    date: CellFormat,
+    datetime: CellFormat,
+    duration: CellFormat,
+}
+
+/// Writes the values of `series` to column `col`, from row `first_row` down.
+fn write_column(
+    sheet: &mut Worksheet,
+    col: u16,
+    first_row: u32,
+    series: &Series,
+    formats: &CellFormats,
+) -> anyhow::Result<()> {
+    let rows = first_row..;
+    match series.dtype() {
+        DataType::Boolean => {
+            for (row, value) in rows.zip(series.bool()?) {
+                if let Some(value) = value {
+                    sheet.write_boolean(row, col, value)?;
+                }
+            }
+        }
+        DataType::String => {
+            for (row, value) in rows.zip(series.str()?) {
+                if let Some(value) = value {
+                    sheet.write_string(row, col, value)?;
+                }
+            }
+        }
+        DataType::Date => {
+            let days = series.to_physical_repr();
+            for (row, value) in rows.zip(days.i32()?) {
+                if let Some(days) = value {
+                    let serial = f64::from(days) + UNIX_EPOCH_SERIAL;
+                    sheet.write_number_with_format(row, col, serial, &formats.date)?;
+                }
+            }
+        }
+        DataType::Datetime(_, _) | DataType::Duration(_) => {
+            // Excel has no time zones, so zoned datetimes are written in UTC.
+            let (dtype, offset, format) = match series.dtype() {
+                DataType::Duration(_) => (
+                    DataType::Duration(TimeUnit::Milliseconds),
+                    0.0,
+                    &formats.duration,
+                ),
+                _ => (
+                    DataType::Datetime(TimeUnit::Milliseconds, None),
+                    UNIX_EPOCH_SERIAL,
+                    &formats.datetime,
+                ),
+            };
+            let millis = series.cast(&dtype)?.to_physical_repr().into_owned();
+            for (row, value) in rows.zip(millis.i64()?) {
+                if let Some(millis) = value {
+                    let serial = millis as f64 / MS_PER_DAY + offset;
+                    sheet.write_number_with_format(row, col, serial, format)?;
+                }
+            }
+        }
+        dtype if dtype.is_numeric() => {
+            let numbers = series.cast(&DataType::Float64)?;
+            for (row, value) in rows.zip(numbers.f64()?) {
+                match value {
+                    Some(n) if n.is_finite() => {
+                        sheet.write_number(row, col, n)?;
+                    }
+                    Some(n) => {
+                        sheet.write_string(row, col, n.to_string())?;
+                    }
+                    None => {}
+                }
+            }
+        }
+        _ => write_column(
+            sheet,
+            col,
+            first_row,
+            &series.cast(&DataType::String)?,
+            formats,
+        )?,
+    }
+    Ok(())
+}
+
+/// Writes `df` as a workbook, splitting it over several sheets if asked to.
+fn write_workbook(df: &DataFrame, args: &Args) -> anyhow::Result<Vec<u8>> {
+    if df.width() > MAX_COLUMNS {
+        bail!(
+            "Excel sheets hold at most {} columns, got: {}",
+            MAX_COLUMNS,
+            df.width()
+        );
+    }
+    let header = args.bool("header")?.unwrap_or(true);
+    let header_rows = usize::from(header);
+    let rows_per_sheet = match args.int("rows-per-sheet")? {
+        Some(n) if n < 1 || n as usize > MAX_ROWS - header_rows => bail!(
+            "rows-per-sheet must be between 1 and {}, got: {}",
+            MAX_ROWS - header_rows,
+            n
+        ),
+        Some(n) => n as usize,
+        None => MAX_ROWS - header_rows,
+    };
+    let sheets = df.height().div_ceil(rows_per_sheet).max(1);
+    if sheets > 1 && !args.bool("split-sheets")?.unwrap_or(false) {
+        bail!(
+            "{} rows don't fit in one sheet of {} rows; pass split-sheets=true",
+            df.height(),
+            rows_per_sheet
+        );
+    }
+    let name = args.string("sheet").unwrap_or_else(|| "Sheet1".to_owned());
+    let freeze_header = args.bool("freeze-header")?.unwrap_or(true);
+    let autofit = args.bool("autofit")?.unwrap_or(true);
+
+    let bold = CellFormat::new().set_bold();
+    let formats = CellFormats {
+        date: CellFormat::new().set_num_format("yyyy-mm-dd"),
+        datetime: CellFormat::new().set_num_format("yyyy-mm-dd hh:mm:ss"),
+        duration: CellFormat::new().set_num_format("[h]:mm:ss"),
+    };
+    let mut workbook = Workbook::new();
+    for index in 0..sheets {
+        let sheet = workbook.add_worksheet();
+        match index {
+            0 => sheet.set_name(name.as_str())?,
+            n => sheet.set_name(format!("{}_{}", name, n + 1))?,
+        };
+        let part = df.slice((index * rows_per_sheet) as i64, rows_per_sheet);
+        for (col, column) in part.get_columns().iter().enumerate() {
+            let col = col as u16;
+            if header {
+                sheet.write_string_with_format(0, col, column.name().as_str(), &bold)?;
+            }
+            let series = column.as_materialized_series();
+            write_column(sheet, col, header_rows as u32, series, &formats)?;
+        }
+        if header && freeze_header {
+            sheet.set_freeze_panes(1, 0)?;
+        }
+        if autofit {
+            sheet.autofit();
+        }
+    }
+    Ok(workbook.save_to_buffer()?)
+}
+
+impl Format for ExcelFormat {
+    fn canonical_name(&self) -> &'static str {
+        "excel"
+    }
+
+    fn aliases(&self) -> Vec<&'static str> {
+        vec!["xlsx", "xls", "ods"]
+    }
+
+    fn extensions(&self) -> Vec<&'static str> {
+        vec!["xlsx", "xlsm", "xlsb", "xls", "ods"]
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        let is_zip = head.starts_with(b"PK\x03\x04");
+        head.starts_with(CFB_MAGIC)
+            || is_zip && head.windows(3).any(|w| w == b"xl/")
+            || is_zip && head.windows(ODS_MIMETYPE.len()).any(|w| w == ODS_MIMETYPE)
+    }
+
+    fn supports_nested(&self) -> bool {
+        false
+    }
+
+    fn read_options(&self) -> FormatOptions {
+        FormatOptions::new()
+            .with(FormatOption::new(
+                "sheet",
+                OptionKind::String,
+                "Sheet name or zero-based index; the first sheet by default",
+            ))
+            .with(FormatOption::new(
+                "range",
+                OptionKind::String,
+                "Cells to read, e.g. A1:D20, or B3 to read from B3 on",
+            ))
+            .with(
+                FormatOption::new(
+                    "header-row",
+                    OptionKind::Int,
+                    "Zero-based row of the header within the range; rows above it are skipped",
+                )
+                .with_default("0"),
+            )
+            .with(
+                FormatOption::new(
+                    "has-header",
+                    OptionKind::Bool,
+                    "Whether there is a header row",
+                )
+                .with_default("true"),
+            )
+    }
+
+    fn write_options(&self) -> FormatOptions {
+        FormatOptions::new()
+            .with(
+                FormatOption::new("sheet", OptionKind::String, "Name of the sheet")
+                    .with_default("Sheet1"),
+            )
+            .with(
+                FormatOption::new("header", OptionKind::Bool, "Write a header row")
+                    .with_default("true"),
+            )
+            .with(
+                FormatOption::new(
+                    "freeze-header",
+                    OptionKind::Bool,
+                    "Keep the header row in view when scrolling",
+                )
+                .with_default("true"),
+            )
+            .with(
+                FormatOption::new(
+                    "autofit",
+                    OptionKind::Bool,
+                    "Fit column widths to their contents",
+                )
+                .with_default("true"),
+            )
+            .with(
+                FormatOption::new(
+                    "split-sheets",
+                    OptionKind::Bool,
+                    "Continue on further sheets when the rows don't fit in one",
+                )
+                .with_default("false"),
+            )
+            .with(FormatOption::new(
+                "rows-per-sheet",
+                OptionKind::Int,
+                "Data rows per sheet; at most Excel's limit of 1048575, the default",
+            ))
+    }
+
+    fn reads_streams(&self) -> bool {
+        true
+    }
+
+    fn read(&self, source: &Source, args: &Args) -> anyhow::Result<LazyFrame> {
+        let sheet = args.string("sheet");
+        let mut range = match source.path() {
+            Some(path) => {
+                let mut workbook = open_workbook_auto(path)
+                    .with_context(|| format!("Unable to open workbook: {}", path))?;
+                sheet_range(&mut workbook, sheet.as_deref())?
+            }
+            None => {
+                let mut workbook = open_workbook_auto_from_rs(Cursor::new(source.read_bytes()?))?;
+                sheet_range(&mut workbook, sheet.as_deref())?
+            }
+        };
+        if let Some(spec) = args.string("range") {
+            range = select_range(&range, &spec)?;
+        }
+        let header_row = match args.int("header-row")? {
+            Some(n) if n < 0 => bail!("header-row must not be negative, got: {}", n),
+            n => n.unwrap_or(0) as usize,
+        };
+        let has_header = args.bool("has-header")?.unwrap_or(true);
+        Ok(frame(&range, header_row, has_header)?.lazy())
+    }
+
+    fn write(&self, sink: &mut Sink, args: &Args, lf: LazyFrame) -> anyhow::Result<()> {
+        let extension = sink
+            .path()
+            .and_then(|path| Path::new(path).extension())
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        if let Some(ext @ ("xls" | "xlsb" | "xlsm" | "ods")) = extension.as_deref() {
+            bail!("Only .xlsx files can be written, not .{}", ext);
+        }
+        let data = write_workbook(&lf.collect()?, args)?;
+        let mut writer = sink.open()?;
+        writer.write_all(&data)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use crate::pandata::{Args, Format, FormatOption, FormatOptions, OptionKind};
+use crate::render::{text_columns, Align};
+use crate::sink::Sink;
+use crate::source::Source;
+use anyhow::bail;
+use polars::prelude::LazyFrame;
+use std::io::Write;
+
+const STYLE: &str = "table { border-collapse: collapse; font-family: sans-serif; }
+th, td { border: 1px solid #ccc; padding: 4px 8px; }
+th { background: #f4f4f4; }";
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Opens a cell with the column's alignment; left is the default.
+fn open_cell(tag: &str, align: Align) -> String {
+    match align {
+        Align::Left => format!("<{}>", tag),
+        Align::Center => format!("<{} style=\"text-align: center\">", tag),
+        Align::Right => format!("<{} style=\"text-align: right\">", tag),
+    }
+}
+
+#[derive(Default)]
+pub struct HtmlFormat;
+
+impl HtmlFormat {
+    pub fn new() -> Self {
+        HtmlFormat {}
+    }
+}
+
+impl Format for HtmlFormat {
+    fn canonical_name(&self) -> &'static str {
+        "html"
+    }
+
+    fn extensions(&self) -> Vec<&'static str> {
+        vec!["html", "htm"]
+    }
+
+    fn supports_nested(&self) -> bool {
+        false
+    }
+
+    fn can_read(&self) -> bool {
+        false
+    }
+
+    fn read_options(&self) -> FormatOptions {
+        FormatOptions::new()
+    }
+
+    fn write_options(&self) -> FormatOptions {
+        FormatOptions::new()
+            .with(
+                FormatOption::new(
+                    "standalone",
+                    OptionKind::Bool,
+                    "Write a whole page rather than just the table",
+                )
+                .with_default("false"),
+            )
+            .with(
+                FormatOption::new("title", OptionKind::String, "Title of a standalone page")
+                    .with_default("pandata"),
+            )
+            .with(FormatOption::new(
+                "class",
+                OptionKind::String,
+                "CSS classes of the table, e.g. \"table striped\"",
+            ))
+            .with(FormatOption::new(
+                "null-value",
+                OptionKind::String,
+                "String for missing values",
+            ))
+    }
+
+    fn read(&self, _source: &Source, _args: &Args) -> anyhow::Result<LazyFrame> {
+        bail!("HTML tables can't be read")
+    }
+
+    fn write(&self, sink: &mut Sink, args: &Args, lf: LazyFrame) -> anyhow::Result<()> {
+        let standalone = args.bool("standalone")?.unwrap_or(false);
+        let null = args.string("null-value").unwrap_or_default();
+        let df = lf.collect()?;
+        let columns = text_columns(&df, &null)?;
+
+        let mut out = sink.open()?;
+        if standalone {
+            let title = args.string("title").unwrap_or_else(|| "pandata".to_owned());
+            writeln!(out, "<!DOCTYPE html>")?;
+            writeln!(out, "<html>")?;
+            writeln!(out, "<head>")?;
+            writeln!(out, "<meta charset=\"utf-8\">")?;
+            writeln!(out, "<title>{}</title>", escape(&title))?;
+            writeln!(out, "<style>\n{}\n</style>", STYLE)?;
+            writeln!(out, "</head>")?;
+            writeln!(out, "<body>")?;
+        }
+        match args.string("class") {
+            Some(class) => writeln!(out, "<table class=\"{}\">", escape(&class))?,
+            None => writeln!(out, "<table>")?,
+        }
+        writeln!(out, "  <thead>")?;
+        let header: String = columns
+            .iter()
+            .map(|c| format!("{}{}</th>", open_cell("th", c.align), escape(&c.name)))
+            .collect();
+        writeln!(out, "    <tr>{}</tr>", header)?;
+        writeln!(out, "  </thead>")?;
+        writeln!(out, "  <tbody>")?;
+        for i in 0..df.height() {
+            let row: String = columns
+                .iter()
+                .map(|c| format!("{}{}</td>", open_cell("td", c.align), escape(&c.cells[i])))
+                .collect();
+            writeln!(out, "    <tr>{}</tr>", row)?;
+        }
+        writeln!(out, "  </tbody>")?;
+        writeln!(out, "</table>")?;
+        if standalone {
+            writeln!(out, "</body>")?;
+            writeln!(out, "</html>")?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+use crate::pandata::{Args, Format, FormatOption, FormatOptions, OptionKind};
+use crate::sink::Sink;
+use crate::source::Source;
+use polars::io::{SerReader, SerWriter};
+use polars::prelude::{
+    IntoLazy, IpcCompression, IpcReader, IpcStreamReader, IpcStreamWriter, IpcWriterOptions,
+    LazyFrame,
+};
+use std::fs::File;
+use std::io::{Cursor, Read, Write};
+use std::path::PathBuf;
+
+const FILE_MAGIC: &[u8] = b"ARROW1";
+const STREAM_CONTINUATION: &[u8] = &[0xff, 0xff, 0xff, 0xff];
+const VARIANTS: &[&str] = &["file", "stream"];
+const COMPRESSIONS: &[&str] = &["uncompressed", "lz4", "zstd"];
+
+#[derive(Default)]
+pub struct IpcFormat;
+
+impl IpcFormat {
+    pub fn new() -> Self {
+        IpcFormat {}
+    }
+}
+
+/// Reads the variant from the file magic. Paths that can't be inspected
+/// without consuming them, such as pipes, are assumed to be streams.
+fn detect_variant(path: &str) -> anyhow::Result<&'static str> {
+    if !std::fs::metadata(path).is_ok_and(|m| m.is_file()) {
+        return Ok("stream");
+    }
+    let mut head = Vec::with_capacity(FILE_MAGIC.len());
+    File::open(path)?
+        .take(FILE_MAGIC.len() as u64)
+        .read_to_end(&mut head)?;
+    Ok(if head == FILE_MAGIC { "file" } else { "stream" })
+}
+
+fn compression(args: &Args) -> Option<IpcCompression> {
+    match args.string("compression").as_deref() {
+        Some("lz4") => Some(IpcCompression::LZ4),
+        Some("zstd") => Some(IpcCompression::ZSTD),
+        _ => None,
+    }
+}
+
+impl Format for IpcFormat {
+    fn canonical_name(&self) -> &'static str {
+        "ipc"
+    }
+
+    fn aliases(&self) -> Vec<&'static str> {
+        vec!["arrow", "feather"]
+    }
+
+    fn extensions(&self) -> Vec<&'static str> {
+        vec!["arrow", "feather", "ipc", "arrows"]
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        head.starts_with(FILE_MAGIC) || head.starts_with(STREAM_CONTINUATION)
+    }
+
+    fn read_options(&self) -> FormatOptions {
+        FormatOptions::new().with(FormatOption::new(
+            "variant",
+            OptionKind::Enum(VARIANTS),
+            "IPC file or stream; detected from the data when omitted",
+        ))
+    }
+
+    fn write_options(&self) -> FormatOptions {
+        FormatOptions::new()
+            .with(
+                FormatOption::new(
+                    "variant",
+                    OptionKind::Enum(VARIANTS),
+                    "Write the random-access file or the streaming variant",
+                )
+                .with_default("file"),
+            )
+            .with(
+                FormatOption::new(
+                    "compression",
+                    OptionKind::Enum(COMPRESSIONS),
+                    "Buffer compression codec",
+                )
+                .with_default("uncompressed"),
+            )
+    }
+
+    fn reads_streams(&self) -> bool {
+        true
+    }
+
+    fn read(&self, source: &Source, args: &Args) -> anyhow::Result<LazyFrame> {
+        let Some(path) = source.path() else {
+            // Streams are read as they arrive; files need random access, so
+            // they are read into memory first.
+            let mut reader = source.open()?;
+            let mut head = Vec::with_capacity(FILE_MAGIC.len());
+            (&mut reader)
+                .take(FILE_MAGIC.len() as u64)
+                .read_to_end(&mut head)?;
+            let mut reader = Cursor::new(head.clone()).chain(reader);
+            let is_file = match args.string("variant") {
+                Some(variant) => variant == "file",
+                None => head == FILE_MAGIC,
+            };
+            if !is_file {
+                return Ok(IpcStreamReader::new(reader).finish()?.lazy());
+            }
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data)?;
+            return Ok(IpcReader::new(Cursor::new(data)).finish()?.lazy());
+        };
+        let variant = match args.string("variant") {
+            Some(variant) => variant,
+            None => detect_variant(path)?.to_owned(),
+        };
+        if variant == "stream" {
+            let df = IpcStreamReader::new(File::open(path)?).finish()?;
+            return Ok(df.lazy());
+        }
+        // `scan_ipc` plans can't feed the streaming sinks yet, so read eagerly;
+        // the file is memory-mapped rather than copied.
+        let df = IpcReader::new(File::open(path)?)
+            .memory_mapped(Some(PathBuf::from(path)))
+            .finish()?;
+        Ok(df.lazy())
+    }
+
+    fn write(&self, sink: &mut Sink, args: &Args, lf: LazyFrame) -> anyhow::Result<()> {
+        if args.string("variant").as_deref() == Some("stream") {
+            let mut df = lf.collect()?;
+            let mut writer = sink.open()?;
+            IpcStreamWriter::new(&mut writer)
+                .with_compression(compression(args))
+                .finish(&mut df)?;
+            writer.flush()?;
+            return Ok(());
+        }
+        let options = IpcWriterOptions {
+            compression: compression(args),
+            maintain_order: true,
+        };
+        sink.with_path(|path| Ok(lf.sink_ipc(path, options)?))
+    }
+}
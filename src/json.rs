@@ -1,11 +1,138 @@
 use crate::pandata::Format;
-use crate::pandata::{Args, FormatOptions};
-use polars::io::SerReader;
+use crate::pandata::{Args, FormatOption, FormatOptions, OptionKind};
+use crate::sink::Sink;
+use crate::source::Source;
+use anyhow::bail;
+use polars::io::mmap::MmapBytesReader;
+use polars::io::{SerReader, SerWriter};
 use polars::prelude::{
-    IntoLazy, JsonFormat as PolarsJsonFormat, JsonReader, JsonWriterOptions, LazyFrame,
+    IntoLazy, JsonFormat as PolarsJsonFormat, JsonReader, JsonWriter, JsonWriterOptions, LazyFrame,
 };
 use std::fs::File;
+use std::io::{Cursor, Read, Write};
 
+const LAYOUTS: &[&str] = &["lines", "array"];
+
+/// Read options shared by the JSON and JSON Lines readers.
+pub(crate) fn json_read_options() -> FormatOptions {
+    FormatOptions::new().with(FormatOption::new(
+        "format",
+        OptionKind::Enum(LAYOUTS),
+        "One object per line, or a top-level array; detected from the data when omitted",
+    ))
+}
+
+/// Write options shared by the JSON and JSON Lines writers.
+pub(crate) fn json_write_options(default_layout: &'static str) -> FormatOptions {
+    FormatOptions::new()
+        .with(
+            FormatOption::new(
+                "format",
+                OptionKind::Enum(LAYOUTS),
+                "One object per line, or a top-level array",
+            )
+            .with_default(default_layout),
+        )
+        .with(
+            FormatOption::new("pretty", OptionKind::Bool, "Indent the output array")
+                .with_default("false"),
+        )
+}
+
+/// Returns true if the first non-whitespace byte of `head` is `byte`.
+pub(crate) fn starts_with_byte(head: &[u8], byte: u8) -> bool {
+    let head = head.strip_prefix(b"\xef\xbb\xbf").unwrap_or(head);
+    head.iter().find(|b| !b.is_ascii_whitespace()) == Some(&byte)
+}
+
+fn layout_of(head: &[u8]) -> &'static str {
+    if starts_with_byte(head, b'[') {
+        "array"
+    } else {
+        "lines"
+    }
+}
+
+fn read_with_layout<R: MmapBytesReader>(reader: R, layout: &str) -> anyhow::Result<LazyFrame> {
+    let json_format = match layout {
+        "array" => PolarsJsonFormat::Json,
+        _ => PolarsJsonFormat::JsonLines,
+    };
+    let df = JsonReader::new(reader)
+        .with_json_format(json_format)
+        .finish()?;
+    Ok(df.lazy())
+}
+
+/// Reads JSON from a file, or from memory for other sources. The layout is
+/// picked from the data unless set, except for pipes, since reading a
+/// sample from one would consume it.
+pub(crate) fn read_json(
+    source: &Source,
+    args: &Args,
+    default_layout: &str,
+) -> anyhow::Result<LazyFrame> {
+    let layout = args.string("format");
+    let Some(path) = source.path() else {
+        let bytes = source.read_bytes()?;
+        let layout =
+            layout.unwrap_or_else(|| layout_of(&bytes[..bytes.len().min(1024)]).to_owned());
+        return read_with_layout(Cursor::new(bytes), &layout);
+    };
+    let layout = match layout {
+        Some(layout) => layout,
+        None if std::fs::metadata(path).is_ok_and(|m| m.is_file()) => {
+            let mut head = Vec::new();
+            File::open(path)?.take(1024).read_to_end(&mut head)?;
+            layout_of(&head).to_owned()
+        }
+        None => default_layout.to_owned(),
+    };
+    read_with_layout(File::open(path)?, &layout)
+}
+
+pub(crate) fn write_json(
+    sink: &mut Sink,
+    args: &Args,
+    lf: LazyFrame,
+    default_layout: &str,
+) -> anyhow::Result<()> {
+    let layout = args
+        .string("format")
+        .unwrap_or_else(|| default_layout.to_owned());
+    let pretty = args.bool("pretty")?.unwrap_or(false);
+    if layout == "lines" {
+        if pretty {
+            bail!("pretty requires format=array");
+        }
+        let options = JsonWriterOptions {
+            maintain_order: true,
+        };
+        return sink.with_path(|path| Ok(lf.sink_json(path, options)?));
+    }
+
+    let mut df = lf.collect()?;
+    let mut file = sink.open()?;
+    if pretty {
+        // polars can't indent its output, so reformat it. serde_json is built
+        // with preserve_order, so the keys keep the column order.
+        let mut buf = Vec::new();
+        JsonWriter::new(&mut buf)
+            .with_json_format(PolarsJsonFormat::Json)
+            .finish(&mut df)?;
+        let value: serde_json::Value = serde_json::from_slice(&buf)?;
+        serde_json::to_writer_pretty(&mut file, &value)?;
+        file.write_all(b"\n")?;
+    } else {
+        JsonWriter::new(&mut file)
+            .with_json_format(PolarsJsonFormat::Json)
+            .finish(&mut df)?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+#[derive(Default)]
 pub struct JsonFormat;
 
 impl JsonFormat {
@@ -19,24 +146,27 @@ impl Format for JsonFormat {
         "json"
     }
 
+    fn sniff(&self, head: &[u8]) -> bool {
+        starts_with_byte(head, b'[')
+    }
+
     fn read_options(&self) -> FormatOptions {
-        FormatOptions::new()
+        json_read_options()
     }
 
-    fn read(&self, path: &str, _args: &Args) -> anyhow::Result<LazyFrame> {
-        let file = File::open(path)?;
+    fn write_options(&self) -> FormatOptions {
+        json_write_options("array")
+    }
+
+    fn reads_streams(&self) -> bool {
+        true
+    }
 
-        let lf = JsonReader::new(file)
-            .with_json_format(PolarsJsonFormat::JsonLines)
-            .finish()?
-            .lazy();
-        Ok(lf)
+    fn read(&self, source: &Source, args: &Args) -> anyhow::Result<LazyFrame> {
+        read_json(source, args, "array")
     }
 
-    fn write(&self, path: &str, _args: &Args, lf: LazyFrame) -> anyhow::Result<()> {
-        let mut options = JsonWriterOptions::default();
-        options.maintain_order = true;
-        lf.sink_json(path, options)?;
-        Ok(())
+    fn write(&self, sink: &mut Sink, args: &Args, lf: LazyFrame) -> anyhow::Result<()> {
+        write_json(sink, args, lf, "array")
     }
 }
@@ -0,0 +1,52 @@
+use crate::json::{json_read_options, json_write_options, read_json, starts_with_byte, write_json};
+use crate::pandata::{Args, Format, FormatOptions};
+use crate::sink::Sink;
+use crate::source::Source;
+use polars::prelude::LazyFrame;
+
+#[derive(Default)]
+pub struct JsonLinesFormat;
+
+impl JsonLinesFormat {
+    pub fn new() -> Self {
+        JsonLinesFormat {}
+    }
+}
+
+impl Format for JsonLinesFormat {
+    fn canonical_name(&self) -> &'static str {
+        "jsonl"
+    }
+
+    fn aliases(&self) -> Vec<&'static str> {
+        vec!["ndjson"]
+    }
+
+    fn extensions(&self) -> Vec<&'static str> {
+        vec!["jsonl", "ndjson"]
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        starts_with_byte(head, b'{')
+    }
+
+    fn read_options(&self) -> FormatOptions {
+        json_read_options()
+    }
+
+    fn write_options(&self) -> FormatOptions {
+        json_write_options("lines")
+    }
+
+    fn reads_streams(&self) -> bool {
+        true
+    }
+
+    fn read(&self, source: &Source, args: &Args) -> anyhow::Result<LazyFrame> {
+        read_json(source, args, "lines")
+    }
+
+    fn write(&self, sink: &mut Sink, args: &Args, lf: LazyFrame) -> anyhow::Result<()> {
+        write_json(sink, args, lf, "lines")
+    }
+}
@@ -1,31 +1,80 @@
-use std::ffi::OsStr;
-use std::path::Path;
-
+mod compression;
 #[cfg(feature = "csv")]
 mod csv;
+#[cfg(feature = "csv")]
+mod dialect;
+#[cfg(feature = "excel")]
+mod excel;
+#[cfg(feature = "html")]
+mod html;
 #[cfg(feature = "json")]
 mod json;
+#[cfg(feature = "json")]
+mod jsonl;
+#[cfg(feature = "markdown")]
+mod markdown;
+mod nested;
 mod pandata;
 #[cfg(feature = "parquet")]
 mod parquet;
+#[cfg(any(feature = "markdown", feature = "html", feature = "table"))]
+mod render;
+mod sink;
+mod source;
+#[cfg(feature = "sql")]
+mod sql;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "table")]
+mod table;
 #[cfg(feature = "tsv")]
 mod tsv;
 
 #[cfg(feature = "avro")]
 mod avro;
+#[cfg(feature = "avro")]
+mod avro_container;
+#[cfg(feature = "avro")]
+mod avsc;
+#[cfg(feature = "ipc")]
+mod ipc;
 
+pub use compression::Compression;
 #[cfg(feature = "csv")]
 pub use csv::CsvFormat;
+#[cfg(feature = "csv")]
+pub use dialect::Dialect;
+#[cfg(feature = "excel")]
+pub use excel::ExcelFormat;
+#[cfg(feature = "html")]
+pub use html::HtmlFormat;
 #[cfg(feature = "json")]
 pub use json::JsonFormat;
-pub use pandata::{Args, Format, FormatOptions, Pandata};
+#[cfg(feature = "json")]
+pub use jsonl::JsonLinesFormat;
+#[cfg(feature = "markdown")]
+pub use markdown::MarkdownFormat;
+pub use nested::{flatten, unflatten, ListMode};
+pub use pandata::{
+    Args, ConvertOptions, Flatten, Format, FormatOption, FormatOptions, Logger, OptionKind, Pandata,
+};
 #[cfg(feature = "parquet")]
 pub use parquet::ParquetFormat;
+pub use sink::Sink;
+pub use source::Source;
+#[cfg(feature = "sql")]
+pub use sql::SqlFormat;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteFormat;
+#[cfg(feature = "table")]
+pub use table::TableFormat;
 #[cfg(feature = "tsv")]
 pub use tsv::TsvFormat;
 
 #[cfg(feature = "avro")]
 pub use avro::AvroFormat;
+#[cfg(feature = "ipc")]
+pub use ipc::IpcFormat;
 
 pub fn build_pandata() -> Pandata {
     let mut pandata = Pandata::new();
@@ -34,24 +83,28 @@ pub fn build_pandata() -> Pandata {
     pandata.add_format(Box::new(CsvFormat::new()));
     #[cfg(feature = "json")]
     pandata.add_format(Box::new(JsonFormat::new()));
+    #[cfg(feature = "json")]
+    pandata.add_format(Box::new(JsonLinesFormat::new()));
     #[cfg(feature = "parquet")]
     pandata.add_format(Box::new(ParquetFormat::new()));
     #[cfg(feature = "tsv")]
     pandata.add_format(Box::new(TsvFormat::new()));
     #[cfg(feature = "avro")]
     pandata.add_format(Box::new(AvroFormat::new()));
+    #[cfg(feature = "ipc")]
+    pandata.add_format(Box::new(IpcFormat::new()));
+    #[cfg(feature = "excel")]
+    pandata.add_format(Box::new(ExcelFormat::new()));
+    #[cfg(feature = "sqlite")]
+    pandata.add_format(Box::new(SqliteFormat::new()));
+    #[cfg(feature = "sql")]
+    pandata.add_format(Box::new(SqlFormat::new()));
+    #[cfg(feature = "markdown")]
+    pandata.add_format(Box::new(MarkdownFormat::new()));
+    #[cfg(feature = "html")]
+    pandata.add_format(Box::new(HtmlFormat::new()));
+    #[cfg(feature = "table")]
+    pandata.add_format(Box::new(TableFormat::new()));
 
     pandata
 }
-
-fn parse_format_path(p: impl AsRef<Path>) -> Option<String> {
-    p.as_ref()
-        .extension()
-        .map(OsStr::to_str)
-        .flatten()
-        .map(str::to_owned)
-}
-
-pub fn parse_format(format: Option<String>, input_path: &str) -> Option<String> {
-    format.or_else(|| parse_format_path(&input_path))
-}
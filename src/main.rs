@@ -1,91 +1,346 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use pandata::build_pandata;
-use pandata::parse_format;
-
-// #[derive(Parser, Debug)]
-// #[command(version, about, long_about = None)]
-// struct Cli {
-//     /// The file to read
-//     #[arg(value_name = "FROM_FILE")]
-//     from_file: Option<String>,
-//
-//     /// The file to write
-//     #[arg(value_name = "TO_FILE")]
-//     to_file: Option<String>,
-//
-//     /// Force the input format
-//     #[arg(short, long, value_name = "FORMAT")]
-//     from: Option<String>,
-//
-//     /// Force the output format
-//     #[arg(short, long, value_name = "FORMAT")]
-//     to: Option<String>,
-// }
-
-#[derive(Default)]
+use pandata::{
+    Args, Compression, ConvertOptions, Flatten, Format, FormatOptions, ListMode, Logger, Pandata,
+    Sink, Source,
+};
+use serde_json::json;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Cli {
-    from_file: Option<String>,
-    to_file: Option<String>,
-    from_format: Option<String>,
-    to_format: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The file to read, or `-` for stdin
+    #[arg(value_name = "FROM_FILE", default_value = "-")]
+    from_file: String,
+
+    /// The file to write, or `-` for stdout
+    #[arg(value_name = "TO_FILE", default_value = "-")]
+    to_file: String,
+
+    /// Force the input format
+    #[arg(short, long, value_name = "FORMAT", value_parser = parse_format_name)]
+    from: Option<String>,
+
+    /// Force the output format
+    #[arg(short, long, value_name = "FORMAT", value_parser = parse_format_name)]
+    to: Option<String>,
+
+    /// Option for the input format, as key=value (repeatable)
+    #[arg(short, long = "read-opt", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    read_opts: Vec<String>,
+
+    /// Option for the output format, as key=value (repeatable)
+    #[arg(short, long = "write-opt", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    write_opts: Vec<String>,
+
+    /// When to flatten struct and list columns into flat columns
+    #[arg(long, value_name = "WHEN", value_parser = ["auto", "always", "never"], default_value = "auto")]
+    flatten: String,
+
+    /// How to flatten list columns: as JSON strings, or one row per element
+    #[arg(long, value_name = "MODE", value_parser = ["json", "explode"], default_value = "json")]
+    lists: String,
+
+    /// Nest `parent.child` columns into struct columns
+    #[arg(long, conflicts_with = "flatten")]
+    unflatten: bool,
+
+    /// Separator between parent and child names of flattened columns
+    #[arg(long, value_name = "SEP", default_value = ".")]
+    nested_separator: String,
+
+    /// Compress the output, overriding its extension (e.g. `.csv.gz`)
+    #[arg(long, value_name = "CODEC", value_parser = Compression::NAMES.to_vec())]
+    compression: Option<String>,
+
+    /// Compression level of the output, e.g. 1-22 for zstd
+    #[arg(long, value_name = "LEVEL", allow_negative_numbers = true)]
+    compression_level: Option<i32>,
+
+    /// Report detected settings, such as a sniffed CSV dialect, on stderr
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List the formats supported by this build and their options
+    Formats {
+        /// Print the list as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the Avro schema (.avsc) that writing the input as Avro would use
+    #[cfg(feature = "avro")]
+    AvroSchema {
+        /// The file to read, or `-` for stdin
+        #[arg(value_name = "FROM_FILE", default_value = "-")]
+        from_file: String,
+
+        /// Force the input format
+        #[arg(short, long, value_name = "FORMAT", value_parser = parse_format_name)]
+        from: Option<String>,
+
+        /// Option for the input format, as key=value (repeatable)
+        #[arg(short, long = "read-opt", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+        read_opts: Vec<String>,
+
+        /// Avro write option, such as name or schema, as key=value (repeatable)
+        #[arg(short, long = "write-opt", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+        write_opts: Vec<String>,
+    },
+}
+
+fn parse_format_name(s: &str) -> Result<String, String> {
+    let pandata = build_pandata();
+    if pandata.has_format(s) {
+        Ok(s.to_owned())
+    } else {
+        Err(format!(
+            "unknown format (available: {})",
+            pandata.format_names().join(", ")
+        ))
+    }
+}
+
+fn parse_key_value(s: &str) -> Result<String, String> {
+    match s.split_once('=') {
+        Some((key, _)) if !key.is_empty() => Ok(s.to_owned()),
+        _ => Err("expected KEY=VALUE".to_owned()),
+    }
+}
+
+fn options_help(options: &FormatOptions, indent: &str) -> String {
+    let mut help = String::new();
+    for option in options.options() {
+        let usage = format!("{}=<{}>", option.name, option.kind.name());
+        help.push_str(&format!("{}{:<24} {}", indent, usage, option.description));
+        if let Some(default) = option.default {
+            help.push_str(&format!(" [default: {}]", default));
+        }
+        help.push('\n');
+    }
+    help
 }
 
-impl Cli {
-    fn parse() -> Cli {
-        let mut cli = Cli::default();
-        let args: Vec<String> = std::env::args().skip(1).collect();
-        let mut idx = 0;
-
-        while idx < args.len() {
-            let arg = &args[idx];
-            match arg.as_str() {
-                "--from" => {
-                    idx += 1;
-                    if let Some(value) = args.get(idx) {
-                        cli.from_format = Some(value.to_owned());
-                    }
-                }
-                "--to" => {
-                    idx += 1;
-                    if let Some(value) = args.get(idx) {
-                        cli.to_format = Some(value.to_owned());
-                    }
-                }
-                _ => {
-                    if cli.from_file.is_none() {
-                        cli.from_file = Some(arg.to_owned());
-                    } else if cli.to_file.is_none() {
-                        cli.to_file = Some(arg.to_owned());
-                    }
-                }
-            }
-            idx += 1;
+fn format_options_help(pandata: &Pandata) -> String {
+    let mut help = String::from("Format options:\n");
+    for format in pandata.formats() {
+        let read_options = format.read_options();
+        if !read_options.is_empty() {
+            help.push_str(&format!("  {} (--read-opt):\n", format.canonical_name()));
+            help.push_str(&options_help(&read_options, "    "));
         }
+        let write_options = format.write_options();
+        if !write_options.is_empty() {
+            help.push_str(&format!("  {} (--write-opt):\n", format.canonical_name()));
+            help.push_str(&options_help(&write_options, "    "));
+        }
+    }
+    help
+}
+
+fn options_json(options: &FormatOptions) -> serde_json::Value {
+    options
+        .options()
+        .map(|option| {
+            json!({
+                "name": option.name,
+                "type": option.kind.name(),
+                "description": option.description,
+                "default": option.default,
+            })
+        })
+        .collect()
+}
+
+fn format_json(format: &dyn Format) -> serde_json::Value {
+    json!({
+        "name": format.canonical_name(),
+        "read": format.can_read(),
+        "write": format.can_write(),
+        "aliases": format.aliases(),
+        "extensions": format.extensions(),
+        "read_options": options_json(&format.read_options()),
+        "write_options": options_json(&format.write_options()),
+    })
+}
 
-        cli
+fn list_formats(pandata: &Pandata, as_json: bool) -> Result<()> {
+    if as_json {
+        let formats: Vec<_> = pandata.formats().into_iter().map(format_json).collect();
+        println!("{}", serde_json::to_string_pretty(&formats)?);
+        return Ok(());
     }
+
+    for format in pandata.formats() {
+        let modes: Vec<&str> = [("read", format.can_read()), ("write", format.can_write())]
+            .into_iter()
+            .filter_map(|(mode, supported)| supported.then_some(mode))
+            .collect();
+        let extensions: Vec<String> = format
+            .extensions()
+            .iter()
+            .map(|ext| format!(".{}", ext))
+            .collect();
+        println!(
+            "{:<10} {:<12} {}",
+            format.canonical_name(),
+            modes.join(", "),
+            extensions.join(" ")
+        );
+        let read_options = format.read_options();
+        if format.can_read() && !read_options.is_empty() {
+            println!("  read options:");
+            print!("{}", options_help(&read_options, "    "));
+        }
+        let write_options = format.write_options();
+        if format.can_write() && !write_options.is_empty() {
+            println!("  write options:");
+            print!("{}", options_help(&write_options, "    "));
+        }
+    }
+    Ok(())
+}
+
+/// Opens the input and detects its format unless given.
+fn open_input(
+    pandata: &Pandata,
+    from_file: &str,
+    from: Option<&str>,
+    log: Option<Logger>,
+) -> Result<(Source, String)> {
+    let mut source = match from_file {
+        "-" => Source::Stdin,
+        path => Source::from(path),
+    };
+    let format = match from {
+        Some(format) => Some(format.to_owned()),
+        None => pandata
+            .detect_source_format(&mut source, log)?
+            .map(str::to_owned),
+    };
+    let Some(format) = format else {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "unable to detect the input format; pass --from",
+            )
+            .exit();
+    };
+    Ok((source, format))
+}
+
+#[cfg(feature = "avro")]
+fn print_avro_schema(
+    pandata: &Pandata,
+    from_file: &str,
+    from: Option<&str>,
+    read_opts: &[String],
+    write_opts: &[String],
+    log: Option<Logger>,
+) -> Result<()> {
+    let format = pandata::AvroFormat::new();
+    let writer_args = Args::from_pairs(write_opts)?;
+    format
+        .write_options()
+        .validate(&writer_args)
+        .context("Invalid write option for format: avro")?;
+    let (mut source, from_format) = open_input(pandata, from_file, from, log)?;
+    let reader_args = Args::from_pairs(read_opts)?.with_logger(log);
+    let lf = pandata.read(&mut source, &from_format, &reader_args)?;
+    println!("{}", format.schema(lf, &writer_args)?);
+    Ok(())
+}
+
+/// Prints notes from the library for `--verbose`.
+fn print_note(message: &str) {
+    eprintln!("pandata: {}", message);
 }
 
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let pandata = build_pandata();
+    let matches = Cli::command()
+        .after_long_help(format_options_help(&pandata))
+        .get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
-    let from_file = match cli.from_file.as_deref() {
-        Some(x) if x != "-" => x,
-        _ => "/dev/stdin",
+    let log = cli.verbose.then_some(print_note as Logger);
+
+    match &cli.command {
+        Some(Command::Formats { json }) => return list_formats(&pandata, *json),
+        #[cfg(feature = "avro")]
+        Some(Command::AvroSchema {
+            from_file,
+            from,
+            read_opts,
+            write_opts,
+        }) => {
+            return print_avro_schema(
+                &pandata,
+                from_file,
+                from.as_deref(),
+                read_opts,
+                write_opts,
+                log,
+            )
+        }
+        None => {}
+    }
+
+    let (source, from_format) = open_input(&pandata, &cli.from_file, cli.from.as_deref(), log)?;
+    let mut sink = match cli.to_file.as_str() {
+        "-" => Sink::Stdout,
+        path => Sink::from(path),
     };
-    let to_file = match cli.to_file.as_deref() {
-        Some(x) if x != "-" => x,
-        _ => "/dev/stdout",
+
+    let Some(to_format) = cli.to.clone().or_else(|| {
+        sink.path()
+            .and_then(|path| pandata.format_for_path(path))
+            .map(str::to_owned)
+    }) else {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "unable to detect the output format; pass --to when writing to stdout",
+            )
+            .exit();
     };
 
-    let from_format = parse_format(cli.from_format.clone(), from_file)
-        .expect("Unable to parse input format. Must be explicit if reading from stdin.");
-    let to_format = parse_format(cli.to_format.clone(), to_file)
-        .expect("Unable to parse output format. Must be explicit if writing to stdout.");
+    let reader_args = Args::from_pairs(&cli.read_opts)?;
+    let writer_args = Args::from_pairs(&cli.write_opts)?;
 
-    let pandata = build_pandata();
+    let options = ConvertOptions {
+        flatten: match cli.flatten.as_str() {
+            "always" => Flatten::Always,
+            "never" => Flatten::Never,
+            _ => Flatten::Auto,
+        },
+        lists: match cli.lists.as_str() {
+            "explode" => ListMode::Explode,
+            _ => ListMode::Json,
+        },
+        unflatten: cli.unflatten,
+        separator: cli.nested_separator.clone(),
+        compression: cli.compression.as_deref().and_then(Compression::from_name),
+        compression_level: cli.compression_level,
+        log,
+    };
 
-    pandata.convert(from_file, to_file, &from_format, &to_format)?;
+    pandata.convert_with_options(
+        source,
+        &mut sink,
+        &from_format,
+        &to_format,
+        &reader_args,
+        &writer_args,
+        &options,
+    )?;
 
     Ok(())
 }
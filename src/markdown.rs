@@ -0,0 +1,110 @@
+use crate::pandata::{Args, Format, FormatOption, FormatOptions, OptionKind};
+use crate::render::{pad, text_columns, text_width, Align};
+use crate::sink::Sink;
+use crate::source::Source;
+use anyhow::bail;
+use polars::prelude::LazyFrame;
+use std::io::Write;
+
+/// Escapes the characters that would end a cell or its row.
+fn escape(s: &str) -> String {
+    s.replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace(['\n', '\r'], "<br>")
+}
+
+/// The delimiter row cell, whose colons set the column's alignment.
+fn delimiter(width: usize, align: Align) -> String {
+    match align {
+        Align::Left => "-".repeat(width),
+        Align::Center => format!(":{}:", "-".repeat(width - 2)),
+        Align::Right => format!("{}:", "-".repeat(width - 1)),
+    }
+}
+
+#[derive(Default)]
+pub struct MarkdownFormat;
+
+impl MarkdownFormat {
+    pub fn new() -> Self {
+        MarkdownFormat {}
+    }
+}
+
+impl Format for MarkdownFormat {
+    fn canonical_name(&self) -> &'static str {
+        "markdown"
+    }
+
+    fn aliases(&self) -> Vec<&'static str> {
+        vec!["md"]
+    }
+
+    fn extensions(&self) -> Vec<&'static str> {
+        vec!["md", "markdown"]
+    }
+
+    fn supports_nested(&self) -> bool {
+        false
+    }
+
+    fn can_read(&self) -> bool {
+        false
+    }
+
+    fn read_options(&self) -> FormatOptions {
+        FormatOptions::new()
+    }
+
+    fn write_options(&self) -> FormatOptions {
+        FormatOptions::new().with(FormatOption::new(
+            "null-value",
+            OptionKind::String,
+            "String for missing values",
+        ))
+    }
+
+    fn read(&self, _source: &Source, _args: &Args) -> anyhow::Result<LazyFrame> {
+        bail!("Markdown tables can't be read")
+    }
+
+    fn write(&self, sink: &mut Sink, args: &Args, lf: LazyFrame) -> anyhow::Result<()> {
+        let null = args.string("null-value").unwrap_or_default();
+        let df = lf.collect()?;
+        let mut columns = text_columns(&df, &null)?;
+        let mut widths = Vec::with_capacity(columns.len());
+        for column in &mut columns {
+            column.name = escape(&column.name);
+            for cell in &mut column.cells {
+                *cell = escape(cell);
+            }
+            let width = column.cells.iter().map(|c| text_width(c)).max();
+            widths.push(width.unwrap_or(0).max(text_width(&column.name)).max(3));
+        }
+
+        let mut out = sink.open()?;
+        let row = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+        let header = columns
+            .iter()
+            .zip(&widths)
+            .map(|(c, &w)| pad(&c.name, w, c.align))
+            .collect();
+        writeln!(out, "{}", row(header))?;
+        let delimiters = columns
+            .iter()
+            .zip(&widths)
+            .map(|(c, &w)| delimiter(w, c.align))
+            .collect();
+        writeln!(out, "{}", row(delimiters))?;
+        for i in 0..df.height() {
+            let cells = columns
+                .iter()
+                .zip(&widths)
+                .map(|(c, &w)| pad(&c.cells[i], w, c.align))
+                .collect();
+            writeln!(out, "{}", row(cells))?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+use anyhow::Result;
+use polars::prelude::{
+    as_struct, col, AnyValue, Column, DataType, Expr, GetOutput, IntoColumn, LazyFrame,
+    StringChunked,
+};
+use serde_json::{Map, Number, Value};
+
+/// How list columns are flattened.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ListMode {
+    /// Encode each list as a JSON string.
+    #[default]
+    Json,
+    /// Emit one row per list element. Several list columns give a row per
+    /// combination of their elements, so lists of any length can be mixed.
+    Explode,
+}
+
+fn is_nested(dtype: &DataType) -> bool {
+    matches!(dtype, DataType::Struct(_) | DataType::List(_))
+}
+
+/// Replaces struct columns by one column per field, named
+/// `parent<separator>child`, and list columns according to `lists`, until
+/// no nested columns remain.
+pub fn flatten(mut lf: LazyFrame, lists: ListMode, separator: &str) -> Result<LazyFrame> {
+    loop {
+        let schema = lf.collect_schema()?;
+        if !schema.iter_values().any(is_nested) {
+            return Ok(lf);
+        }
+        let mut exprs = Vec::new();
+        let mut explode = Vec::new();
+        for (name, dtype) in schema.iter() {
+            match dtype {
+                DataType::Struct(fields) => {
+                    for field in fields {
+                        exprs.push(
+                            col(name.clone())
+                                .struct_()
+                                .field_by_name(field.name())
+                                .alias(format!("{}{}{}", name, separator, field.name())),
+                        );
+                    }
+                }
+                DataType::List(_) if lists == ListMode::Explode => {
+                    exprs.push(col(name.clone()));
+                    explode.push(col(name.clone()));
+                }
+                DataType::List(_) => exprs.push(json_encode(col(name.clone()))),
+                _ => exprs.push(col(name.clone())),
+            }
+        }
+        lf = lf.select(exprs);
+        // Exploding the columns together would need lists of equal length.
+        for column in explode {
+            lf = lf.explode([column]);
+        }
+    }
+}
+
+/// Nests columns whose names contain `separator` into struct columns, the
+/// inverse of `flatten` for struct fields. Groups whose prefix collides with
+/// an existing column are left as they are.
+pub fn unflatten(lf: LazyFrame, separator: &str) -> Result<LazyFrame> {
+    let mut lf = lf;
+    let schema = lf.collect_schema()?;
+    if !schema.iter_names().any(|name| name.contains(separator)) {
+        return Ok(lf);
+    }
+    let entries = schema
+        .iter_names()
+        .map(|name| (name.to_string(), col(name.clone())))
+        .collect();
+    Ok(lf.select(nest(entries, separator)))
+}
+
+enum Node {
+    Leaf(String, Expr),
+    Group(String, Vec<(String, Expr)>),
+}
+
+fn nest(entries: Vec<(String, Expr)>, separator: &str) -> Vec<Expr> {
+    let mut nodes: Vec<Node> = Vec::new();
+    for (name, expr) in entries {
+        let split = name
+            .split_once(separator)
+            .filter(|(prefix, rest)| !prefix.is_empty() && !rest.is_empty());
+        let Some((prefix, rest)) = split else {
+            nodes.push(Node::Leaf(name, expr));
+            continue;
+        };
+        let group = nodes.iter_mut().find_map(|node| match node {
+            Node::Group(p, children) if p == prefix => Some(children),
+            _ => None,
+        });
+        match group {
+            Some(children) => children.push((rest.to_owned(), expr)),
+            None => nodes.push(Node::Group(
+                prefix.to_owned(),
+                vec![(rest.to_owned(), expr)],
+            )),
+        }
+    }
+
+    let leaves: Vec<String> = nodes
+        .iter()
+        .filter_map(|node| match node {
+            Node::Leaf(name, _) => Some(name.clone()),
+            Node::Group(..) => None,
+        })
+        .collect();
+    let mut exprs = Vec::new();
+    for node in nodes {
+        match node {
+            Node::Leaf(name, expr) => exprs.push(expr.alias(name)),
+            Node::Group(prefix, children) if leaves.contains(&prefix) => {
+                for (rest, expr) in children {
+                    exprs.push(expr.alias(format!("{}{}{}", prefix, separator, rest)));
+                }
+            }
+            Node::Group(prefix, children) => {
+                exprs.push(as_struct(nest(children, separator)).alias(prefix));
+            }
+        }
+    }
+    exprs
+}
+
+fn json_encode(expr: Expr) -> Expr {
+    expr.map(
+        |c: Column| {
+            let s = c.as_materialized_series();
+            let encoded: StringChunked = (0..s.len())
+                .map(|i| match s.get(i) {
+                    Ok(AnyValue::Null) | Err(_) => None,
+                    Ok(av) => Some(to_json(&av).to_string()),
+                })
+                .collect();
+            Ok(Some(encoded.with_name(s.name().clone()).into_column()))
+        },
+        GetOutput::from_type(DataType::String),
+    )
+}
+
+fn to_json(av: &AnyValue) -> Value {
+    match av {
+        AnyValue::Null => Value::Null,
+        AnyValue::Boolean(b) => Value::Bool(*b),
+        AnyValue::String(s) => Value::String((*s).to_owned()),
+        AnyValue::StringOwned(s) => Value::String(s.to_string()),
+        AnyValue::Int8(v) => (*v).into(),
+        AnyValue::Int16(v) => (*v).into(),
+        AnyValue::Int32(v) => (*v).into(),
+        AnyValue::Int64(v) => (*v).into(),
+        AnyValue::UInt8(v) => (*v).into(),
+        AnyValue::UInt16(v) => (*v).into(),
+        AnyValue::UInt32(v) => (*v).into(),
+        AnyValue::UInt64(v) => (*v).into(),
+        AnyValue::Float32(v) => Number::from_f64(f64::from(*v)).map_or(Value::Null, Value::Number),
+        AnyValue::Float64(v) => Number::from_f64(*v).map_or(Value::Null, Value::Number),
+        AnyValue::List(s) => Value::Array(
+            (0..s.len())
+                .map(|i| s.get(i).map_or(Value::Null, |av| to_json(&av)))
+                .collect(),
+        ),
+        AnyValue::Struct(_, _, fields) => Value::Object(
+            fields
+                .iter()
+                .zip(av._iter_struct_av())
+                .map(|(field, av)| (field.name().to_string(), to_json(&av)))
+                .collect::<Map<_, _>>(),
+        ),
+        AnyValue::StructOwned(payload) => Value::Object(
+            payload
+                .1
+                .iter()
+                .zip(&payload.0)
+                .map(|(field, av)| (field.name().to_string(), to_json(av)))
+                .collect::<Map<_, _>>(),
+        ),
+        other => Value::String(other.to_string()),
+    }
+}
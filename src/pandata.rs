@@ -1,104 +1,578 @@
+use crate::compression::Compression;
+use crate::nested::{flatten, unflatten, ListMode};
+use crate::sink::Sink;
+use crate::source::Source;
 use anyhow::Context;
 use anyhow::Result;
+use anyhow::{anyhow, bail};
 use polars::prelude::LazyFrame;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
+use std::path::Path;
 
+/// Receives notes on settings detected while converting, such as a sniffed
+/// CSV dialect.
+pub type Logger = fn(&str);
+
+/// Replaces a compressed source by its decompressed content, noting the
+/// compression found.
+fn decompress(source: &mut Source, log: Option<Logger>) -> Result<()> {
+    if let (Some(compression), Some(log)) = (source.decompress()?, log) {
+        log(&format!("decompressing {} input", compression.name()));
+    }
+    Ok(())
+}
+
+/// Number of leading bytes passed to `Format::sniff`.
+pub const SNIFF_LEN: usize = 4096;
+
+#[derive(Default)]
 pub struct Pandata {
     formats: HashMap<String, Box<dyn Format>>,
+    /// Lowercased aliases, mapped to canonical names.
+    aliases: HashMap<String, &'static str>,
+    /// Lowercased extensions, mapped to canonical names.
+    extensions: HashMap<String, &'static str>,
 }
 
 impl Pandata {
     pub fn new() -> Self {
         Pandata {
             formats: HashMap::new(),
+            aliases: HashMap::new(),
+            extensions: HashMap::new(),
         }
     }
 
     pub fn add_format(&mut self, format: Box<dyn Format>) {
-        self.formats
-            .insert(format.canonical_name().to_owned(), format);
+        let name = format.canonical_name();
+        for alias in format.aliases() {
+            self.aliases.insert(alias.to_ascii_lowercase(), name);
+        }
+        for ext in format.extensions() {
+            self.extensions.insert(ext.to_ascii_lowercase(), name);
+        }
+        self.formats.insert(name.to_owned(), format);
+    }
+
+    /// Looks up a format by canonical name, alias or extension, ignoring case.
+    pub fn get(&self, name: &str) -> Option<&dyn Format> {
+        let name = name.to_ascii_lowercase();
+        let canonical = match self.formats.get_key_value(&name) {
+            Some((canonical, _)) => canonical.as_str(),
+            None => self
+                .aliases
+                .get(&name)
+                .or_else(|| self.extensions.get(&name))
+                .copied()?,
+        };
+        self.formats.get(canonical).map(Box::as_ref)
+    }
+
+    pub fn has_format(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Detects the format of a path from its extension. Compound extensions
+    /// are tried longest first, and a trailing compression extension is
+    /// skipped, so `data.csv.gz` is detected as `csv`.
+    pub fn format_for_path(&self, path: impl AsRef<Path>) -> Option<&'static str> {
+        let file_name = path.as_ref().file_name()?.to_str()?.to_ascii_lowercase();
+        let mut stem = file_name.as_str();
+        loop {
+            let found = stem
+                .match_indices('.')
+                .map(|(idx, _)| &stem[idx + 1..])
+                .find_map(|ext| self.extensions.get(ext));
+            if let Some(name) = found {
+                return Some(name);
+            }
+            // Look past a compression extension, as in `data.csv.gz`.
+            Compression::from_path(stem)?;
+            stem = stem.rsplit_once('.')?.0;
+        }
+    }
+
+    /// Detects a format from the leading bytes of its content.
+    pub fn sniff_format(&self, head: &[u8]) -> Option<&'static str> {
+        self.formats()
+            .into_iter()
+            .find(|f| f.can_read() && f.sniff(head))
+            .map(|f| f.canonical_name())
+    }
+
+    /// Detects the format of a file from its content, falling back to its
+    /// extension.
+    pub fn detect_format(&self, path: &str) -> Result<Option<&'static str>> {
+        self.detect_source_format(&mut Source::from(path), None)
+    }
+
+    /// Detects the format of `source` from its content, falling back to the
+    /// extension of its path. Compressed sources are replaced by their
+    /// decompressed content and streams are left ready to be read again.
+    /// Notes on what was done are sent to `log`.
+    pub fn detect_source_format(
+        &self,
+        source: &mut Source,
+        log: Option<Logger>,
+    ) -> Result<Option<&'static str>> {
+        let path = source.path().map(str::to_owned);
+        decompress(source, log)?;
+        let head = source.peek(SNIFF_LEN)?;
+        Ok(self
+            .sniff_format(&head)
+            .or_else(|| path.and_then(|path| self.format_for_path(path))))
+    }
+
+    pub fn formats(&self) -> Vec<&dyn Format> {
+        let mut formats: Vec<&dyn Format> = self.formats.values().map(Box::as_ref).collect();
+        formats.sort_unstable_by_key(|f| f.canonical_name());
+        formats
+    }
+
+    pub fn format_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.formats.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
     }
 
     pub fn convert(
         &self,
-        from_path: &str,
-        to_path: &str,
+        source: impl Into<Source>,
+        sink: impl Into<Sink>,
+        from_format: &str,
+        to_format: &str,
+    ) -> Result<()> {
+        self.convert_with_args(
+            source,
+            sink,
+            from_format,
+            to_format,
+            &Args::new(),
+            &Args::new(),
+        )
+    }
+
+    pub fn convert_with_args(
+        &self,
+        source: impl Into<Source>,
+        sink: impl Into<Sink>,
         from_format: &str,
         to_format: &str,
+        reader_args: &Args,
+        writer_args: &Args,
     ) -> Result<()> {
-        let reader_args = Args::new();
+        self.convert_with_options(
+            source,
+            &mut sink.into(),
+            from_format,
+            to_format,
+            reader_args,
+            writer_args,
+            &ConvertOptions::default(),
+        )
+    }
+
+    /// Reads `source` as `format` after validating `args`, first
+    /// decompressing it and spooling streams to a temporary file if the
+    /// format can't read them. The frame may read `source` lazily, so keep
+    /// it until the frame is collected. Notes are sent to the logger of
+    /// `args`; see `Args::with_logger`.
+    pub fn read(&self, source: &mut Source, format: &str, args: &Args) -> Result<LazyFrame> {
         let reader = self
-            .formats
-            .get(from_format)
-            .with_context(|| format!("No reader for format: {}", from_format))?;
-        let writer_args = Args::new();
+            .get(format)
+            .filter(|f| f.can_read())
+            .with_context(|| format!("No reader for format: {}", format))?;
+        reader
+            .read_options()
+            .validate(args)
+            .with_context(|| format!("Invalid read option for format: {}", format))?;
+        decompress(source, args.log)?;
+        if source.is_stream() && !reader.reads_streams() {
+            args.log("copying the input to a temporary file");
+            source.spool()?;
+        }
+        reader.read(source, args)
+    }
+
+    /// Writes `lf` to `sink` as `format` after validating `args`.
+    pub fn write(&self, sink: &mut Sink, format: &str, args: &Args, lf: LazyFrame) -> Result<()> {
+        self.writer(format, args)?.write(sink, args, lf)
+    }
+
+    fn writer(&self, format: &str, args: &Args) -> Result<&dyn Format> {
         let writer = self
-            .formats
-            .get(to_format)
-            .with_context(|| format!("No writer for format: {}", to_format))?;
-        let _ = reader.read_options().options().count();
-        let _ = writer.read_options().options().count();
-        let lf = reader.read(from_path, &reader_args)?;
-        writer.write(to_path, &writer_args, lf)?;
-        Ok(())
+            .get(format)
+            .filter(|f| f.can_write())
+            .with_context(|| format!("No writer for format: {}", format))?;
+        writer
+            .write_options()
+            .validate(args)
+            .with_context(|| format!("Invalid write option for format: {}", format))?;
+        Ok(writer)
+    }
+
+    /// Converts `source` to `sink`. In-memory sinks hold the output
+    /// afterwards; see `Sink::into_bytes`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn convert_with_options(
+        &self,
+        source: impl Into<Source>,
+        sink: &mut Sink,
+        from_format: &str,
+        to_format: &str,
+        reader_args: &Args,
+        writer_args: &Args,
+        options: &ConvertOptions,
+    ) -> Result<()> {
+        // Fail on bad write options before reading anything.
+        let writer = self.writer(to_format, writer_args)?;
+        let compression = options
+            .compression
+            .or_else(|| sink.path().and_then(Compression::from_path))
+            .unwrap_or(Compression::Uncompressed);
+        if let Some(level) = options.compression_level {
+            compression.check_level(level)?;
+        }
+        let reader_args = reader_args
+            .clone()
+            .with_logger(options.log.or(reader_args.log));
+        let writer_args = writer_args
+            .clone()
+            .with_logger(options.log.or(writer_args.log));
+        let mut source = source.into();
+        let mut lf = self.read(&mut source, from_format, &reader_args)?;
+        if options.unflatten {
+            lf = unflatten(lf, &options.separator)?;
+        }
+        let flatten_nested = match options.flatten {
+            Flatten::Auto => !writer.supports_nested(),
+            Flatten::Always => true,
+            Flatten::Never => false,
+        };
+        if flatten_nested {
+            lf = flatten(lf, options.lists, &options.separator)?;
+        }
+        if compression != Compression::Uncompressed {
+            writer_args.log(format!(
+                "compressing the output with {}",
+                compression.name()
+            ));
+        }
+        sink.with_compression(compression, options.compression_level, |sink| {
+            writer.write(sink, &writer_args, lf)
+        })
+    }
+}
+
+/// When nested (struct and list) columns are flattened.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Flatten {
+    /// Only for writers that can't hold nested columns.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Settings for a conversion that aren't specific to either format.
+#[derive(Clone, Debug)]
+pub struct ConvertOptions {
+    pub flatten: Flatten,
+    pub lists: ListMode,
+    /// Nest `parent.child` columns into structs before writing.
+    pub unflatten: bool,
+    /// Joins parent and child names in flattened column names.
+    pub separator: String,
+    /// Compression of the output. By default it follows the extension of
+    /// the output path, e.g. `.csv.gz`.
+    pub compression: Option<Compression>,
+    /// Codec-specific compression level.
+    pub compression_level: Option<i32>,
+    /// Receives notes on detected settings; by default they are dropped.
+    pub log: Option<Logger>,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            flatten: Flatten::Auto,
+            lists: ListMode::Json,
+            unflatten: false,
+            separator: ".".to_owned(),
+            compression: None,
+            compression_level: None,
+            log: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OptionKind {
+    /// A single ASCII byte, e.g. a separator.
+    Char,
+    Bool,
+    Int,
+    String,
+    /// One of a fixed set of values.
+    Enum(&'static [&'static str]),
+    /// Comma-separated values, or the option given more than once.
+    List,
+}
+
+impl OptionKind {
+    pub fn name(&self) -> String {
+        match self {
+            OptionKind::Char => "char".to_owned(),
+            OptionKind::Bool => "bool".to_owned(),
+            OptionKind::Int => "int".to_owned(),
+            OptionKind::String => "string".to_owned(),
+            OptionKind::Enum(values) => values.join("|"),
+            OptionKind::List => "list".to_owned(),
+        }
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct FormatOption {
+    pub name: &'static str,
+    pub kind: OptionKind,
+    pub description: &'static str,
+    pub default: Option<&'static str>,
+}
+
+impl FormatOption {
+    pub fn new(name: &'static str, kind: OptionKind, description: &'static str) -> Self {
+        Self {
+            name,
+            kind,
+            description,
+            default: None,
+        }
+    }
+
+    pub fn with_default(mut self, default: &'static str) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    pub fn validate(&self, value: &str) -> Result<()> {
+        match &self.kind {
+            OptionKind::Char => parse_char(self.name, value).map(|_| ()),
+            OptionKind::Bool => parse_bool(self.name, value).map(|_| ()),
+            OptionKind::Int => parse_int(self.name, value).map(|_| ()),
+            OptionKind::String | OptionKind::List => Ok(()),
+            OptionKind::Enum(values) => {
+                if values.contains(&value) {
+                    Ok(())
+                } else {
+                    bail!(
+                        "{} must be one of {}, got: {}",
+                        self.name,
+                        values.join(", "),
+                        value
+                    )
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct FormatOptions {
-    keys: HashSet<String>,
+    options: Vec<FormatOption>,
 }
 
 impl FormatOptions {
     pub fn new() -> Self {
-        let v: [&str; 0] = [];
-        Self::from_keys(v)
+        Self {
+            options: Vec::new(),
+        }
+    }
+
+    pub fn with(mut self, option: FormatOption) -> Self {
+        self.options.push(option);
+        self
     }
 
-    pub fn from_keys<'a>(it: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
-        let keys = it.into_iter().map(|s| s.as_ref().to_owned()).collect();
-        Self { keys }
+    pub fn get(&self, name: &str) -> Option<&FormatOption> {
+        self.options.iter().find(|o| o.name == name)
     }
 
-    pub fn options(&self) -> impl Iterator<Item = &String> {
-        self.keys.iter()
+    pub fn options(&self) -> impl Iterator<Item = &FormatOption> {
+        self.options.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.options.is_empty()
+    }
+
+    pub fn validate(&self, args: &Args) -> Result<()> {
+        let mut keys: Vec<&String> = args.keys().collect();
+        keys.sort_unstable();
+        for key in keys {
+            let Some(option) = self.get(key) else {
+                let mut valid: Vec<&str> = self.options.iter().map(|o| o.name).collect();
+                valid.sort_unstable();
+                let valid = if valid.is_empty() {
+                    "none".to_owned()
+                } else {
+                    valid.join(", ")
+                };
+                bail!("Unknown option: {} (valid options: {})", key, valid);
+            };
+            let values = args.args.get(key.as_str()).into_iter().flatten();
+            for value in values {
+                option.validate(value)?;
+            }
+        }
+        Ok(())
     }
 }
 
-#[derive(Default)]
+fn parse_char(key: &str, value: &str) -> Result<u8> {
+    match value {
+        "\\t" => Ok(b'\t'),
+        _ if value.len() == 1 && value.is_ascii() => Ok(value.as_bytes()[0]),
+        _ => bail!("{} must be a single ASCII byte, got: {:?}", key, value),
+    }
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" => Ok(false),
+        _ => bail!("{} must be true or false, got: {}", key, value),
+    }
+}
+
+fn parse_int(key: &str, value: &str) -> Result<i64> {
+    value
+        .parse()
+        .with_context(|| format!("{} must be an integer, got: {}", key, value))
+}
+
+#[derive(Clone, Default)]
 pub struct Args {
     args: HashMap<String, Vec<String>>,
+    log: Option<Logger>,
 }
 
 impl Args {
     pub fn new() -> Self {
         Self {
             args: HashMap::new(),
+            log: None,
+        }
+    }
+
+    /// Sends notes on settings detected while reading or writing to `log`.
+    pub fn with_logger(mut self, log: Option<Logger>) -> Self {
+        self.log = log;
+        self
+    }
+
+    pub(crate) fn log(&self, message: impl std::fmt::Display) {
+        if let Some(log) = self.log {
+            log(&message.to_string());
         }
     }
 
+    /// Builds `Args` from `key=value` pairs, as given on the command line.
+    pub fn from_pairs(it: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Self> {
+        let mut args = Self::new();
+        for pair in it {
+            let pair = pair.as_ref();
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Expected key=value, got: {}", pair))?;
+            if key.is_empty() {
+                bail!("Missing option name in: {}", pair);
+            }
+            args.insert(key, value);
+        }
+        Ok(args)
+    }
+
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.args.entry(key.into()).or_default().push(value.into());
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.args.keys()
+    }
+
     pub fn list(&self, key: &str) -> Option<Vec<String>> {
-        self.args.get(key).map(|x| x.clone())
+        self.args.get(key).map(|values| {
+            values
+                .iter()
+                .flat_map(|v| v.split(','))
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_owned)
+                .collect()
+        })
     }
+
     pub fn string(&self, key: &str) -> Option<String> {
-        match self.list(key) {
-            Some(v) if v.len() >= 1 => v.first().map(|x| x.clone()),
-            _ => None,
-        }
+        self.args.get(key).and_then(|v| v.first()).cloned()
+    }
+
+    pub fn char(&self, key: &str) -> Result<Option<u8>> {
+        self.string(key).map(|s| parse_char(key, &s)).transpose()
+    }
+
+    pub fn bool(&self, key: &str) -> Result<Option<bool>> {
+        self.string(key).map(|s| parse_bool(key, &s)).transpose()
     }
 
-    pub fn char(&self, key: &str) -> Option<u8> {
-        self.string(key)
-            .map(|s| s.as_bytes().get(0).map(|x| *x))
-            .flatten()
+    pub fn int(&self, key: &str) -> Result<Option<i64>> {
+        self.string(key).map(|s| parse_int(key, &s)).transpose()
     }
 }
 
 pub trait Format {
     fn canonical_name(&self) -> &'static str;
 
+    /// Other names accepted for `--from`/`--to`.
+    fn aliases(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
+    /// File extensions, without the leading dot, that select this format.
+    fn extensions(&self) -> Vec<&'static str> {
+        vec![self.canonical_name()]
+    }
+
+    /// Returns true if `head`, the first bytes of some input, looks like
+    /// this format.
+    fn sniff(&self, _head: &[u8]) -> bool {
+        false
+    }
+
+    /// Whether struct and list columns can be written as they are.
+    fn supports_nested(&self) -> bool {
+        true
+    }
+
+    fn can_read(&self) -> bool {
+        true
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
     fn read_options(&self) -> FormatOptions;
 
-    fn read(&self, path: &str, args: &Args) -> Result<LazyFrame>;
+    fn write_options(&self) -> FormatOptions;
+
+    /// Whether `read` accepts stdin and other streams. Otherwise they are
+    /// copied to a temporary file first.
+    fn reads_streams(&self) -> bool {
+        false
+    }
+
+    fn read(&self, source: &Source, args: &Args) -> Result<LazyFrame>;
 
-    fn write(&self, path: &str, args: &Args, lf: LazyFrame) -> Result<()>;
+    fn write(&self, sink: &mut Sink, args: &Args, lf: LazyFrame) -> Result<()>;
 }
@@ -1,6 +1,167 @@
-use crate::pandata::{Args, Format, FormatOptions};
-use polars::prelude::{LazyFrame, ParquetWriteOptions, ScanArgsParquet};
+use crate::pandata::{Args, Format, FormatOption, FormatOptions, OptionKind};
+use crate::sink::Sink;
+use crate::source::Source;
+use anyhow::{bail, Context};
+use polars::io::mmap::MmapBytesReader;
+use polars::io::SerReader;
+use polars::prelude::{
+    col, concat, BrotliLevel, GzipLevel, IdxSize, IntoLazy, LazyFrame, ParallelStrategy,
+    ParquetCompression, ParquetReader, ParquetWriteOptions, ParquetWriter, ScanArgsParquet,
+    StatisticsOptions, UnionArgs, ZstdLevel,
+};
+use std::fs::File;
+use std::io::{Cursor, Write};
 
+const COMPRESSIONS: &[&str] = &["uncompressed", "snappy", "gzip", "brotli", "zstd", "lz4"];
+const PARALLEL: &[&str] = &["auto", "columns", "row-groups", "prefiltered", "none"];
+
+/// Parses row group indices such as `0,2,5-7` (ranges are inclusive).
+fn parse_row_groups(spec: &[String], count: usize) -> anyhow::Result<Vec<usize>> {
+    let mut indices = Vec::new();
+    for item in spec {
+        let parse = |s: &str| -> anyhow::Result<usize> {
+            let idx: usize = s
+                .trim()
+                .parse()
+                .with_context(|| format!("row-groups must be indices or ranges, got: {}", item))?;
+            if idx >= count {
+                bail!("row group {} is out of range, the file has {}", idx, count);
+            }
+            Ok(idx)
+        };
+        match item.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse(start)?, parse(end)?);
+                if start > end {
+                    bail!("row-groups range must be ascending, got: {}", item);
+                }
+                indices.extend(start..=end)
+            }
+            None => indices.push(parse(item)?),
+        }
+    }
+    indices.sort_unstable();
+    indices.dedup();
+    Ok(indices)
+}
+
+/// The number of rows in each row group.
+fn row_group_rows<R: MmapBytesReader>(reader: &mut ParquetReader<R>) -> anyhow::Result<Vec<usize>> {
+    Ok(reader
+        .get_metadata()?
+        .row_groups
+        .iter()
+        .map(|rg| rg.num_rows())
+        .collect())
+}
+
+/// Restricts `lf`, a single file whose row groups hold `rows` rows each, to
+/// the given row groups by slicing each contiguous run of them.
+fn select_row_groups(lf: LazyFrame, rows: &[usize], spec: &[String]) -> anyhow::Result<LazyFrame> {
+    let indices = parse_row_groups(spec, rows.len())?;
+
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for idx in indices {
+        match runs.last_mut() {
+            Some((_, end)) if *end == idx => *end = idx + 1,
+            _ => runs.push((idx, idx + 1)),
+        }
+    }
+    let slices: Vec<LazyFrame> = runs
+        .into_iter()
+        .map(|(start, end)| {
+            let offset: usize = rows[..start].iter().sum();
+            let len: usize = rows[start..end].iter().sum();
+            lf.clone().slice(offset as i64, len as IdxSize)
+        })
+        .collect();
+    if slices.is_empty() {
+        return Ok(lf.limit(0));
+    }
+    Ok(concat(slices, UnionArgs::default())?)
+}
+
+/// Builds the codec, checking the level against the range each codec accepts.
+fn compression(args: &Args) -> anyhow::Result<ParquetCompression> {
+    let codec = args
+        .string("compression")
+        .unwrap_or_else(|| "zstd".to_owned());
+    let level = args.int("compression-level")?;
+    let check_range = |min: i64, max: i64| -> anyhow::Result<()> {
+        match level {
+            Some(level) if level < min || level > max => bail!(
+                "compression-level for {} must be between {} and {}, got: {}",
+                codec,
+                min,
+                max,
+                level
+            ),
+            _ => Ok(()),
+        }
+    };
+    let compression = match codec.as_str() {
+        "gzip" => {
+            check_range(0, 10)?;
+            let level = level.map(|l| GzipLevel::try_new(l as u8)).transpose()?;
+            ParquetCompression::Gzip(level)
+        }
+        "brotli" => {
+            check_range(0, 11)?;
+            let level = level.map(|l| BrotliLevel::try_new(l as u32)).transpose()?;
+            ParquetCompression::Brotli(level)
+        }
+        "zstd" => {
+            check_range(1, 22)?;
+            let level = level.map(|l| ZstdLevel::try_new(l as i32)).transpose()?;
+            ParquetCompression::Zstd(level)
+        }
+        other => {
+            if level.is_some() {
+                bail!("compression-level is not supported for {}", other);
+            }
+            match other {
+                "uncompressed" => ParquetCompression::Uncompressed,
+                "snappy" => ParquetCompression::Snappy,
+                _ => ParquetCompression::Lz4Raw,
+            }
+        }
+    };
+    Ok(compression)
+}
+
+fn positive(args: &Args, key: &str) -> anyhow::Result<Option<usize>> {
+    match args.int(key)? {
+        Some(n) if n <= 0 => bail!("{} must be positive, got: {}", key, n),
+        n => Ok(n.map(|n| n as usize)),
+    }
+}
+
+/// Scans the files at `path`, which may be a glob pattern or a directory.
+fn scan(path: &str, args: &Args) -> anyhow::Result<LazyFrame> {
+    let mut scan_args = ScanArgsParquet::default();
+    if let Some(glob) = args.bool("glob")? {
+        scan_args.glob = glob;
+    }
+    scan_args.hive_options.enabled = args.bool("hive-partitioning")?;
+    scan_args.parallel = match args.string("parallel").as_deref() {
+        Some("columns") => ParallelStrategy::Columns,
+        Some("row-groups") => ParallelStrategy::RowGroups,
+        Some("prefiltered") => ParallelStrategy::Prefiltered,
+        Some("none") => ParallelStrategy::None,
+        _ => ParallelStrategy::Auto,
+    };
+    let mut lf = LazyFrame::scan_parquet(path, scan_args)?;
+    if let Some(row_groups) = args.list("row-groups") {
+        if !std::fs::metadata(path).is_ok_and(|m| m.is_file()) {
+            bail!("row-groups can only be used when reading a single file");
+        }
+        let rows = row_group_rows(&mut ParquetReader::new(File::open(path)?))?;
+        lf = select_row_groups(lf, &rows, &row_groups)?;
+    }
+    Ok(lf)
+}
+
+#[derive(Default)]
 pub struct ParquetFormat;
 
 impl ParquetFormat {
@@ -14,19 +175,140 @@ impl Format for ParquetFormat {
         "parquet"
     }
 
+    fn aliases(&self) -> Vec<&'static str> {
+        vec!["pq"]
+    }
+
+    fn extensions(&self) -> Vec<&'static str> {
+        vec!["parquet", "pq"]
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        head.starts_with(b"PAR1")
+    }
+
     fn read_options(&self) -> FormatOptions {
         FormatOptions::new()
+            .with(FormatOption::new(
+                "columns",
+                OptionKind::List,
+                "Only read these columns",
+            ))
+            .with(FormatOption::new(
+                "row-groups",
+                OptionKind::List,
+                "Only read these row groups of a single file, e.g. 0,2,5-7",
+            ))
+            .with(
+                FormatOption::new(
+                    "glob",
+                    OptionKind::Bool,
+                    "Expand glob patterns such as data/*.parquet",
+                )
+                .with_default("true"),
+            )
+            .with(FormatOption::new(
+                "hive-partitioning",
+                OptionKind::Bool,
+                "Add columns from key=value directories; on by default for directories",
+            ))
+            .with(
+                FormatOption::new(
+                    "parallel",
+                    OptionKind::Enum(PARALLEL),
+                    "How to parallelize reading",
+                )
+                .with_default("auto"),
+            )
+    }
+
+    fn write_options(&self) -> FormatOptions {
+        FormatOptions::new()
+            .with(
+                FormatOption::new(
+                    "compression",
+                    OptionKind::Enum(COMPRESSIONS),
+                    "Compression codec",
+                )
+                .with_default("zstd"),
+            )
+            .with(FormatOption::new(
+                "compression-level",
+                OptionKind::Int,
+                "Codec level: gzip 0-10, brotli 0-11, zstd 1-22",
+            ))
+            .with(FormatOption::new(
+                "row-group-size",
+                OptionKind::Int,
+                "Rows per row group",
+            ))
+            .with(FormatOption::new(
+                "data-page-size",
+                OptionKind::Int,
+                "Target data page size in bytes",
+            ))
+            .with(
+                FormatOption::new(
+                    "statistics",
+                    OptionKind::Bool,
+                    "Write min, max and null count statistics",
+                )
+                .with_default("true"),
+            )
     }
 
-    fn read(&self, path: &str, _args: &Args) -> anyhow::Result<LazyFrame> {
-        let args = ScanArgsParquet::default();
-        let lf = LazyFrame::scan_parquet(path, args)?;
+    fn read(&self, source: &Source, args: &Args) -> anyhow::Result<LazyFrame> {
+        let mut lf = match source {
+            Source::Bytes(bytes) => {
+                // These only tune scanning by path.
+                for key in ["glob", "hive-partitioning", "parallel"] {
+                    if args.string(key).is_some() {
+                        bail!("{} can only be used when reading from a path", key);
+                    }
+                }
+                let mut reader = ParquetReader::new(Cursor::new(bytes.clone()));
+                let rows = row_group_rows(&mut reader)?;
+                let mut lf = reader.finish()?.lazy();
+                if let Some(row_groups) = args.list("row-groups") {
+                    lf = select_row_groups(lf, &rows, &row_groups)?;
+                }
+                lf
+            }
+            _ => scan(source.require_path("parquet")?, args)?,
+        };
+        if let Some(columns) = args.list("columns") {
+            lf = lf.select(columns.iter().map(|c| col(c.as_str())).collect::<Vec<_>>());
+        }
         Ok(lf)
     }
 
-    fn write(&self, path: &str, _args: &Args, lf: LazyFrame) -> anyhow::Result<()> {
-        let options = ParquetWriteOptions::default();
-        lf.sink_parquet(path, options)?;
-        Ok(())
+    fn write(&self, sink: &mut Sink, args: &Args, lf: LazyFrame) -> anyhow::Result<()> {
+        let statistics = match args.bool("statistics")? {
+            Some(false) => StatisticsOptions::empty(),
+            _ => StatisticsOptions::default(),
+        };
+        let row_group_size = positive(args, "row-group-size")?;
+        let options = ParquetWriteOptions {
+            compression: compression(args)?,
+            statistics,
+            row_group_size,
+            data_page_size: positive(args, "data-page-size")?,
+            maintain_order: true,
+        };
+        if row_group_size.is_some() {
+            // The streaming sink cuts row groups at chunk boundaries, ignoring
+            // the requested size, so write eagerly instead.
+            let mut df = lf.collect()?;
+            let mut writer = sink.open()?;
+            ParquetWriter::new(&mut writer)
+                .with_compression(options.compression)
+                .with_statistics(options.statistics)
+                .with_row_group_size(options.row_group_size)
+                .with_data_page_size(options.data_page_size)
+                .finish(&mut df)?;
+            writer.flush()?;
+            return Ok(());
+        }
+        sink.with_path(|path| Ok(lf.sink_parquet(path, options)?))
     }
 }
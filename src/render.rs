@@ -0,0 +1,78 @@
+//! Cell text shared by the markdown, html and table writers.
+use anyhow::bail;
+use polars::prelude::{DataFrame, DataType};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    /// Numbers are right-aligned so their digits line up, booleans centred
+    /// and everything else left-aligned.
+    pub(crate) fn of(dtype: &DataType) -> Self {
+        match dtype {
+            DataType::Boolean => Align::Center,
+            dtype if dtype.is_numeric() => Align::Right,
+            _ => Align::Left,
+        }
+    }
+}
+
+/// A column as text.
+pub(crate) struct TextColumn {
+    pub name: String,
+    pub align: Align,
+    pub cells: Vec<String>,
+}
+
+/// The columns of `df` as text, with `null` for missing values.
+pub(crate) fn text_columns(df: &DataFrame, null: &str) -> anyhow::Result<Vec<TextColumn>> {
+    if df.width() == 0 {
+        bail!("Can't write a table without columns");
+    }
+    df.get_columns()
+        .iter()
+        .map(|column| {
+            let series = column.as_materialized_series();
+            let cells = match series.dtype() {
+                DataType::Binary => series
+                    .binary()?
+                    .into_iter()
+                    .map(|v| {
+                        v.map_or_else(|| null.to_owned(), |b| String::from_utf8_lossy(b).into())
+                    })
+                    .collect(),
+                _ => series
+                    .cast(&DataType::String)?
+                    .str()?
+                    .into_iter()
+                    .map(|v| v.unwrap_or(null).to_owned())
+                    .collect(),
+            };
+            Ok(TextColumn {
+                name: column.name().to_string(),
+                align: Align::of(column.dtype()),
+                cells,
+            })
+        })
+        .collect()
+}
+
+/// The width of `s` in a terminal, taking each character as one column.
+pub(crate) fn text_width(s: &str) -> usize {
+    s.chars().count()
+}
+
+/// Pads `s` with spaces to `width`.
+pub(crate) fn pad(s: &str, width: usize, align: Align) -> String {
+    let fill = width.saturating_sub(text_width(s));
+    let (left, right) = match align {
+        Align::Left => (0, fill),
+        Align::Center => (fill / 2, fill - fill / 2),
+        Align::Right => (fill, 0),
+    };
+    format!("{}{}{}", " ".repeat(left), s, " ".repeat(right))
+}
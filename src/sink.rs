@@ -0,0 +1,97 @@
+use crate::compression::Compression;
+use anyhow::{Context, Result};
+use bytes::{BufMut, Bytes, BytesMut};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use tempfile::NamedTempFile;
+
+/// The output of a conversion.
+pub enum Sink {
+    /// A file, created or truncated.
+    Path(String),
+    /// Standard output.
+    Stdout,
+    /// An in-memory buffer, appended to.
+    Bytes(BytesMut),
+}
+
+impl Sink {
+    /// An empty in-memory sink.
+    pub fn bytes() -> Self {
+        Sink::Bytes(BytesMut::new())
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Sink::Path(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// The data written to an in-memory sink.
+    pub fn into_bytes(self) -> Option<Bytes> {
+        match self {
+            Sink::Bytes(bytes) => Some(bytes.freeze()),
+            _ => None,
+        }
+    }
+
+    /// Opens the sink for writing. Callers should flush the writer.
+    pub fn open(&mut self) -> Result<Box<dyn Write + Send + '_>> {
+        Ok(match self {
+            Sink::Path(path) => Box::new(BufWriter::new(
+                File::create(&*path).with_context(|| format!("Unable to create: {}", path))?,
+            )),
+            Sink::Stdout => Box::new(BufWriter::new(io::stdout())),
+            Sink::Bytes(bytes) => Box::new(bytes.writer()),
+        })
+    }
+
+    /// Calls `write` with a path to write to: the sink's own, or else a
+    /// temporary file that is then copied to the sink. This lets the
+    /// streaming sinks of polars, which only write files, feed any sink
+    /// without holding the data in memory.
+    pub fn with_path(&mut self, write: impl FnOnce(&str) -> Result<()>) -> Result<()> {
+        if let Some(path) = self.path() {
+            return write(path);
+        }
+        let file = NamedTempFile::new()?;
+        write(file.path().to_str().context("Invalid temp file path")?)?;
+        let mut writer = self.open()?;
+        io::copy(&mut file.reopen()?, &mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Calls `write` with a sink whose content is then compressed into this
+    /// one.
+    pub fn with_compression(
+        &mut self,
+        compression: Compression,
+        level: Option<i32>,
+        write: impl FnOnce(&mut Sink) -> Result<()>,
+    ) -> Result<()> {
+        if compression == Compression::Uncompressed {
+            return write(self);
+        }
+        let file = NamedTempFile::new()?;
+        let path = file.path().to_str().context("Invalid temp file path")?;
+        write(&mut Sink::from(path))?;
+        let mut writer = self.open()?;
+        compression.compress(&mut file.reopen()?, &mut writer, level)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl From<&str> for Sink {
+    fn from(path: &str) -> Self {
+        Sink::Path(path.to_owned())
+    }
+}
+
+impl From<String> for Sink {
+    fn from(path: String) -> Self {
+        Sink::Path(path)
+    }
+}
@@ -0,0 +1,148 @@
+use crate::compression::Compression;
+use anyhow::{bail, Context, Result};
+use bytes::Bytes;
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, Read};
+use std::sync::Mutex;
+use tempfile::NamedTempFile;
+
+/// The input of a conversion.
+pub enum Source {
+    /// A file, a directory of partitions or a glob pattern.
+    Path(String),
+    /// Standard input.
+    Stdin,
+    /// Data already in memory.
+    Bytes(Bytes),
+    /// Any other stream. It can only be read once; see `Source::stream`.
+    Stream(Mutex<Option<Box<dyn Read + Send>>>),
+    /// Stdin or a stream copied to a temporary file, for formats that seek.
+    Spooled(NamedTempFile),
+}
+
+impl Source {
+    pub fn stream(reader: impl Read + Send + 'static) -> Self {
+        Source::Stream(Mutex::new(Some(Box::new(reader))))
+    }
+
+    /// The path of a file-backed source.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Source::Path(path) => Some(path),
+            Source::Spooled(file) => file.path().to_str(),
+            _ => None,
+        }
+    }
+
+    /// Whether the source can only be read once, front to back.
+    pub fn is_stream(&self) -> bool {
+        matches!(self, Source::Stdin | Source::Stream(_))
+    }
+
+    /// Opens the source for reading from the start.
+    pub fn open(&self) -> Result<Box<dyn Read + Send>> {
+        Ok(match self {
+            Source::Path(path) => Box::new(BufReader::new(
+                File::open(path).with_context(|| format!("Unable to open: {}", path))?,
+            )),
+            Source::Stdin => Box::new(io::stdin()),
+            Source::Bytes(bytes) => Box::new(Cursor::new(bytes.clone())),
+            Source::Stream(stream) => stream
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Input stream is poisoned"))?
+                .take()
+                .context("Input stream was already read")?,
+            Source::Spooled(file) => Box::new(BufReader::new(file.reopen()?)),
+        })
+    }
+
+    /// Reads the whole source into memory.
+    pub fn read_bytes(&self) -> Result<Bytes> {
+        if let Source::Bytes(bytes) = self {
+            return Ok(bytes.clone());
+        }
+        let mut buf = Vec::new();
+        self.open()?.read_to_end(&mut buf)?;
+        Ok(buf.into())
+    }
+
+    /// Returns the path of a file-backed source, or an error naming `format`
+    /// for streams.
+    pub fn require_path(&self, format: &str) -> Result<&str> {
+        match self.path() {
+            Some(path) => Ok(path),
+            None => bail!(
+                "{} can only be read from a file; spool the input first",
+                format
+            ),
+        }
+    }
+
+    /// Reads up to `len` leading bytes without consuming them. Streams are
+    /// replaced by one that replays those bytes first.
+    pub fn peek(&mut self, len: usize) -> Result<Vec<u8>> {
+        let mut head = Vec::with_capacity(len);
+        match self {
+            Source::Bytes(bytes) => head.extend_from_slice(&bytes[..len.min(bytes.len())]),
+            Source::Stdin | Source::Stream(_) => {
+                let mut reader = self.open()?;
+                (&mut reader).take(len as u64).read_to_end(&mut head)?;
+                *self = Source::stream(Cursor::new(head.clone()).chain(reader));
+            }
+            _ => {
+                let path = self.path().context("Invalid temp file path")?;
+                // Directories and glob patterns have no content to peek at.
+                if std::fs::metadata(path).is_ok_and(|m| m.is_file()) {
+                    self.open()?.take(len as u64).read_to_end(&mut head)?;
+                }
+            }
+        }
+        Ok(head)
+    }
+
+    /// Replaces a compressed source by a stream of its decompressed content,
+    /// returning the compression found.
+    pub fn decompress(&mut self) -> Result<Option<Compression>> {
+        let Some(compression) = Compression::sniff(&self.peek(16)?) else {
+            return Ok(None);
+        };
+        let reader = compression.decoder(self.open()?)?;
+        *self = Source::stream(reader);
+        Ok(Some(compression))
+    }
+
+    /// Copies a stream to a temporary file, so it can be read by path.
+    pub fn spool(&mut self) -> Result<()> {
+        if !self.is_stream() {
+            return Ok(());
+        }
+        let mut file = NamedTempFile::new()?;
+        io::copy(&mut self.open()?, &mut file)?;
+        *self = Source::Spooled(file);
+        Ok(())
+    }
+}
+
+impl From<&str> for Source {
+    fn from(path: &str) -> Self {
+        Source::Path(path.to_owned())
+    }
+}
+
+impl From<String> for Source {
+    fn from(path: String) -> Self {
+        Source::Path(path)
+    }
+}
+
+impl From<Bytes> for Source {
+    fn from(bytes: Bytes) -> Self {
+        Source::Bytes(bytes)
+    }
+}
+
+impl From<Vec<u8>> for Source {
+    fn from(bytes: Vec<u8>) -> Self {
+        Source::Bytes(bytes.into())
+    }
+}
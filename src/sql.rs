@@ -0,0 +1,366 @@
+use crate::pandata::{Args, Format, FormatOption, FormatOptions, OptionKind};
+use crate::sink::Sink;
+use crate::source::Source;
+use anyhow::bail;
+use polars::prelude::{Column, DataFrame, DataType, LazyFrame, TimeUnit};
+use std::fmt::Write as _;
+use std::io::Write;
+
+const DIALECTS: &[&str] = &["ansi", "postgres", "mysql", "sqlite"];
+
+/// The SQL dialect a script is written for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SqlDialect {
+    Ansi,
+    Postgres,
+    Mysql,
+    Sqlite,
+}
+
+impl SqlDialect {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "postgres" => SqlDialect::Postgres,
+            "mysql" => SqlDialect::Mysql,
+            "sqlite" => SqlDialect::Sqlite,
+            _ => SqlDialect::Ansi,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            SqlDialect::Ansi => "ansi",
+            SqlDialect::Postgres => "postgres",
+            SqlDialect::Mysql => "mysql",
+            SqlDialect::Sqlite => "sqlite",
+        }
+    }
+
+    fn quote(self, name: &str) -> String {
+        match self {
+            SqlDialect::Mysql => format!("`{}`", name.replace('`', "``")),
+            _ => format!("\"{}\"", name.replace('"', "\"\"")),
+        }
+    }
+
+    /// A string literal. MySQL treats backslashes as escapes by default.
+    fn string(self, s: &str) -> String {
+        let mut out = String::with_capacity(s.len() + 2);
+        out.push('\'');
+        for c in s.chars() {
+            match c {
+                '\'' => out.push_str("''"),
+                '\\' if self == SqlDialect::Mysql => out.push_str("\\\\"),
+                '\0' if self == SqlDialect::Mysql => out.push_str("\\0"),
+                c => out.push(c),
+            }
+        }
+        out.push('\'');
+        out
+    }
+
+    fn bool(self, b: bool) -> &'static str {
+        match (self, b) {
+            (SqlDialect::Sqlite, true) => "1",
+            (SqlDialect::Sqlite, false) => "0",
+            (_, true) => "TRUE",
+            (_, false) => "FALSE",
+        }
+    }
+
+    fn binary(self, bytes: &[u8]) -> String {
+        let mut hex = String::with_capacity(bytes.len() * 2);
+        for b in bytes {
+            write!(hex, "{:02X}", b).unwrap();
+        }
+        match self {
+            // Postgres reads hex escapes into bytea columns.
+            SqlDialect::Postgres => format!("'\\x{}'", hex),
+            _ => format!("X'{}'", hex),
+        }
+    }
+
+    /// Fails if `df` holds floats the dialect has no literal for.
+    fn check_floats(self, df: &DataFrame) -> anyhow::Result<()> {
+        if matches!(self, SqlDialect::Postgres | SqlDialect::Sqlite) {
+            return Ok(());
+        }
+        for column in df.get_columns() {
+            if !column.dtype().is_float() {
+                continue;
+            }
+            let floats = column.cast(&DataType::Float64)?;
+            let bad = floats.f64()?.into_iter().flatten().find(|f| !f.is_finite());
+            if let Some(f) = bad {
+                bail!(
+                    "Column {} holds {}, which the {} dialect can't represent",
+                    column.name(),
+                    f,
+                    self.name()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// A float literal. Only Postgres has infinities and NaN; SQLite reads
+    /// NaN as NULL and overflows to infinity.
+    fn float(self, f: f64) -> String {
+        match self {
+            _ if f.is_finite() => format!("{:?}", f),
+            SqlDialect::Postgres if f.is_nan() => "'NaN'".to_owned(),
+            SqlDialect::Postgres if f > 0.0 => "'Infinity'".to_owned(),
+            SqlDialect::Postgres => "'-Infinity'".to_owned(),
+            SqlDialect::Sqlite if f > 0.0 => "9e999".to_owned(),
+            SqlDialect::Sqlite if f < 0.0 => "-9e999".to_owned(),
+            _ => "NULL".to_owned(),
+        }
+    }
+
+    /// A typed literal for dates and times, written `DATE '2024-01-02'`
+    /// where the dialect has them and as plain strings otherwise.
+    fn temporal(self, keyword: &str, s: &str) -> String {
+        match self {
+            SqlDialect::Ansi | SqlDialect::Postgres => format!("{} {}", keyword, self.string(s)),
+            SqlDialect::Mysql | SqlDialect::Sqlite => self.string(s),
+        }
+    }
+
+    fn column_type(self, dtype: &DataType) -> String {
+        use SqlDialect::*;
+        let name = match (self, dtype) {
+            (Sqlite, DataType::Boolean) => "BOOLEAN",
+            (Sqlite, dtype) if dtype.is_integer() => "INTEGER",
+            (Sqlite, DataType::Float32 | DataType::Float64) => "REAL",
+            (Sqlite, DataType::Binary) => "BLOB",
+            (Sqlite, DataType::Date) => "DATE",
+            (Sqlite, DataType::Datetime(_, _)) => "TIMESTAMP",
+            (Sqlite, DataType::Decimal(_, _)) => "NUMERIC",
+            (Sqlite, _) => "TEXT",
+
+            (_, DataType::Boolean) => "BOOLEAN",
+            (Mysql, DataType::Int8) => "TINYINT",
+            (Mysql, DataType::UInt8) => "TINYINT UNSIGNED",
+            (Mysql, DataType::UInt16) => "SMALLINT UNSIGNED",
+            (Mysql, DataType::UInt32) => "INT UNSIGNED",
+            (Mysql, DataType::UInt64) => "BIGINT UNSIGNED",
+            (_, DataType::Int8 | DataType::Int16 | DataType::UInt8) => "SMALLINT",
+            (Mysql, DataType::Int32) => "INT",
+            (_, DataType::Int32 | DataType::UInt16) => "INTEGER",
+            (_, DataType::Int64 | DataType::UInt32) => "BIGINT",
+            (_, DataType::UInt64) => "NUMERIC(20)",
+            (Mysql, DataType::Float32) => "FLOAT",
+            (_, DataType::Float32) => "REAL",
+            (Mysql, DataType::Float64) => "DOUBLE",
+            (_, DataType::Float64) => "DOUBLE PRECISION",
+            (_, DataType::Decimal(precision, scale)) => {
+                return format!(
+                    "DECIMAL({}, {})",
+                    precision.unwrap_or(38),
+                    scale.unwrap_or(0)
+                )
+            }
+            (Postgres, DataType::Binary) => "BYTEA",
+            (Mysql, DataType::Binary) => "LONGBLOB",
+            (_, DataType::Binary) => "BLOB",
+            (_, DataType::Date) => "DATE",
+            (Mysql, DataType::Datetime(TimeUnit::Milliseconds, _)) => "DATETIME(3)",
+            (Mysql, DataType::Datetime(_, _)) => "DATETIME(6)",
+            (Postgres, DataType::Datetime(_, Some(_))) => "TIMESTAMPTZ",
+            (_, DataType::Datetime(_, Some(_))) => "TIMESTAMP WITH TIME ZONE",
+            (_, DataType::Datetime(_, None)) => "TIMESTAMP",
+            (Mysql, _) => "LONGTEXT",
+            (Postgres, _) => "TEXT",
+            (Ansi, _) => "VARCHAR",
+        };
+        name.to_owned()
+    }
+
+    /// The literals of `column`, one per row.
+    fn literals(self, column: &Column) -> anyhow::Result<Vec<String>> {
+        let series = column.as_materialized_series();
+        let null = || "NULL".to_owned();
+        Ok(match series.dtype() {
+            DataType::Boolean => series
+                .bool()?
+                .into_iter()
+                .map(|v| v.map_or_else(null, |b| self.bool(b).to_owned()))
+                .collect(),
+            dtype if dtype.is_integer() || dtype.is_decimal() => series
+                .cast(&DataType::String)?
+                .str()?
+                .into_iter()
+                .map(|v| v.map_or_else(null, str::to_owned))
+                .collect(),
+            dtype if dtype.is_float() => series
+                .cast(&DataType::Float64)?
+                .f64()?
+                .into_iter()
+                .map(|v| v.map_or_else(null, |f| self.float(f)))
+                .collect(),
+            DataType::Binary => series
+                .binary()?
+                .into_iter()
+                .map(|v| v.map_or_else(null, |b| self.binary(b)))
+                .collect(),
+            DataType::Date => series
+                .cast(&DataType::String)?
+                .str()?
+                .into_iter()
+                .map(|v| v.map_or_else(null, |s| self.temporal("DATE", s)))
+                .collect(),
+            DataType::Datetime(unit, tz) => {
+                // Zoned values are stored in UTC, so write them as such.
+                let (keyword, suffix) = match (self, tz) {
+                    (SqlDialect::Postgres, Some(_)) => ("TIMESTAMPTZ", "+00:00"),
+                    (SqlDialect::Ansi, Some(_)) => ("TIMESTAMP WITH TIME ZONE", "+00:00"),
+                    _ => ("TIMESTAMP", ""),
+                };
+                series
+                    .cast(&DataType::Datetime(*unit, None))?
+                    .cast(&DataType::String)?
+                    .str()?
+                    .into_iter()
+                    .map(|v| {
+                        v.map_or_else(null, |s| {
+                            self.temporal(keyword, &format!("{}{}", s, suffix))
+                        })
+                    })
+                    .collect()
+            }
+            _ => series
+                .cast(&DataType::String)?
+                .str()?
+                .into_iter()
+                .map(|v| v.map_or_else(null, |s| self.string(s)))
+                .collect(),
+        })
+    }
+}
+
+/// Writes `df` as a script creating and filling `table`.
+fn write_script(
+    out: &mut dyn Write,
+    df: &DataFrame,
+    dialect: SqlDialect,
+    table: &str,
+    create: bool,
+    batch_size: usize,
+) -> anyhow::Result<()> {
+    let table = dialect.quote(table);
+    if create {
+        writeln!(out, "CREATE TABLE {} (", table)?;
+        let columns = df.get_columns();
+        for (i, column) in columns.iter().enumerate() {
+            let comma = if i + 1 < columns.len() { "," } else { "" };
+            writeln!(
+                out,
+                "  {} {}{}",
+                dialect.quote(column.name()),
+                dialect.column_type(column.dtype()),
+                comma
+            )?;
+        }
+        writeln!(out, ");")?;
+    }
+
+    let names: Vec<String> = df
+        .get_columns()
+        .iter()
+        .map(|c| dialect.quote(c.name()))
+        .collect();
+    let names = names.join(", ");
+    for offset in (0..df.height()).step_by(batch_size) {
+        let batch = df.slice(offset as i64, batch_size);
+        let columns = batch
+            .get_columns()
+            .iter()
+            .map(|c| dialect.literals(c))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        writeln!(out, "INSERT INTO {} ({}) VALUES", table, names)?;
+        for i in 0..batch.height() {
+            let row: Vec<&str> = columns.iter().map(|c| c[i].as_str()).collect();
+            let end = if i + 1 < batch.height() { "," } else { ";" };
+            writeln!(out, "  ({}){}", row.join(", "), end)?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct SqlFormat;
+
+impl SqlFormat {
+    pub fn new() -> Self {
+        SqlFormat {}
+    }
+}
+
+impl Format for SqlFormat {
+    fn canonical_name(&self) -> &'static str {
+        "sql"
+    }
+
+    fn supports_nested(&self) -> bool {
+        false
+    }
+
+    fn can_read(&self) -> bool {
+        false
+    }
+
+    fn read_options(&self) -> FormatOptions {
+        FormatOptions::new()
+    }
+
+    fn write_options(&self) -> FormatOptions {
+        FormatOptions::new()
+            .with(
+                FormatOption::new(
+                    "dialect",
+                    OptionKind::Enum(DIALECTS),
+                    "Database the script is for; sets quoting, types and literals",
+                )
+                .with_default("ansi"),
+            )
+            .with(
+                FormatOption::new("table", OptionKind::String, "Table to create and fill")
+                    .with_default("data"),
+            )
+            .with(
+                FormatOption::new(
+                    "create-table",
+                    OptionKind::Bool,
+                    "Start with a CREATE TABLE statement",
+                )
+                .with_default("true"),
+            )
+            .with(
+                FormatOption::new("batch-size", OptionKind::Int, "Rows per INSERT statement")
+                    .with_default("1000"),
+            )
+    }
+
+    fn read(&self, _source: &Source, _args: &Args) -> anyhow::Result<LazyFrame> {
+        bail!("SQL scripts can't be read")
+    }
+
+    fn write(&self, sink: &mut Sink, args: &Args, lf: LazyFrame) -> anyhow::Result<()> {
+        let dialect = SqlDialect::from_name(&args.string("dialect").unwrap_or_default());
+        let table = args.string("table").unwrap_or_else(|| "data".to_owned());
+        let create = args.bool("create-table")?.unwrap_or(true);
+        let batch_size = match args.int("batch-size")? {
+            Some(n) if n < 1 => bail!("batch-size must be positive, got: {}", n),
+            n => n.unwrap_or(1000) as usize,
+        };
+        let df = lf.collect()?;
+        if df.width() == 0 {
+            bail!("Can't write a table without columns");
+        }
+        dialect.check_floats(&df)?;
+        let mut out = sink.open()?;
+        write_script(&mut out, &df, dialect, &table, create, batch_size)?;
+        out.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,418 @@
+use crate::pandata::{Args, Format, FormatOption, FormatOptions, OptionKind};
+use crate::sink::Sink;
+use crate::source::Source;
+use anyhow::{bail, Context};
+use polars::export::chrono::{NaiveDate, NaiveDateTime};
+use polars::prelude::{
+    Column, DataFrame, DataType, IntoLazy, LazyFrame, NamedFrom, Series, TimeUnit,
+};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection, OptionalExtension};
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+const MAGIC: &[u8] = b"SQLite format 3\0";
+const IF_EXISTS: &[&str] = &["fail", "replace", "append"];
+/// The most parameters one statement may bind.
+const MAX_VARIABLES: usize = 32_766;
+
+#[derive(Default)]
+pub struct SqliteFormat;
+
+impl SqliteFormat {
+    pub fn new() -> Self {
+        SqliteFormat {}
+    }
+}
+
+/// Quotes an identifier such as a table or column name.
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn table_names(conn: &Connection) -> anyhow::Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type IN ('table', 'view') \
+         AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )?;
+    let names = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    Ok(names)
+}
+
+/// The query reading `args`: the `query` option, all of the `table` option,
+/// or all of the only table in the database.
+fn select(conn: &Connection, args: &Args) -> anyhow::Result<String> {
+    match (args.string("query"), args.string("table")) {
+        (Some(_), Some(_)) => bail!("Pass either table or query, not both"),
+        (Some(query), None) => Ok(query),
+        (None, Some(table)) => Ok(format!("SELECT * FROM {}", quote(&table))),
+        (None, None) => match table_names(conn)?.as_slice() {
+            [table] => Ok(format!("SELECT * FROM {}", quote(table))),
+            [] => bail!("The database has no tables"),
+            tables => bail!(
+                "The database has several tables; pass table= or query=: {}",
+                tables.join(", ")
+            ),
+        },
+    }
+}
+
+/// Parses every non-null value, or gives up on the column.
+fn parse_all<T>(values: &[Option<String>], parse: fn(&str) -> Option<T>) -> Option<Vec<Option<T>>> {
+    values
+        .iter()
+        .map(|v| match v {
+            Some(s) => parse(s).map(Some),
+            None => Some(None),
+        })
+        .collect()
+}
+
+/// Days since the epoch of an ISO 8601 date.
+fn parse_date(s: &str) -> Option<i32> {
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?;
+    Some((date - NaiveDate::default()).num_days() as i32)
+}
+
+/// Milliseconds since the epoch of an ISO 8601 date and time, with either a
+/// space or a `T` between them.
+fn parse_datetime(s: &str) -> Option<i64> {
+    [
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+    .map(|datetime| datetime.and_utc().timestamp_millis())
+}
+
+/// The type of a column with declared type `decl` but no values, following
+/// SQLite's type affinity rules.
+fn affinity(decl: &str) -> DataType {
+    let decl = decl.to_ascii_uppercase();
+    if decl.contains("INT") {
+        DataType::Int64
+    } else if decl.contains("CHAR") || decl.contains("CLOB") || decl.contains("TEXT") {
+        DataType::String
+    } else if decl.contains("BLOB") {
+        DataType::Binary
+    } else if decl.contains("REAL") || decl.contains("FLOA") || decl.contains("DOUB") {
+        DataType::Float64
+    } else {
+        DataType::String
+    }
+}
+
+/// Builds a column from SQLite values, which are typed per value rather than
+/// per column. The declared type picks out booleans and temporal columns,
+/// which SQLite stores as integers and text.
+fn column(name: &str, decl: Option<&str>, values: Vec<Value>) -> anyhow::Result<Column> {
+    let name = name.into();
+    let mut ints = true;
+    let mut numbers = true;
+    let mut blobs = true;
+    let mut any = false;
+    for value in &values {
+        match value {
+            Value::Null => continue,
+            Value::Integer(_) => blobs = false,
+            Value::Real(_) => (ints, blobs) = (false, false),
+            Value::Text(_) => (ints, numbers, blobs) = (false, false, false),
+            Value::Blob(_) => (ints, numbers) = (false, false),
+        }
+        any = true;
+    }
+    let decl = decl.unwrap_or_default().to_ascii_uppercase();
+    let series = if !any {
+        Series::full_null(name, values.len(), &affinity(&decl))
+    } else if ints {
+        let values: Vec<Option<i64>> = values
+            .iter()
+            .map(|v| match v {
+                Value::Integer(i) => Some(*i),
+                _ => None,
+            })
+            .collect();
+        let series = Series::new(name, values);
+        if decl.contains("BOOL") {
+            series.cast(&DataType::Boolean)?
+        } else {
+            series
+        }
+    } else if numbers {
+        let values: Vec<Option<f64>> = values
+            .iter()
+            .map(|v| match v {
+                Value::Integer(i) => Some(*i as f64),
+                Value::Real(f) => Some(*f),
+                _ => None,
+            })
+            .collect();
+        Series::new(name, values)
+    } else if blobs {
+        let values: Vec<Option<Vec<u8>>> = values
+            .into_iter()
+            .map(|v| match v {
+                Value::Blob(b) => Some(b),
+                _ => None,
+            })
+            .collect();
+        Series::new(name, values)
+    } else {
+        let values: Vec<Option<String>> = values
+            .into_iter()
+            .map(|v| match v {
+                Value::Null => None,
+                Value::Integer(i) => Some(i.to_string()),
+                Value::Real(f) => Some(f.to_string()),
+                Value::Text(s) => Some(s),
+                Value::Blob(b) => Some(String::from_utf8_lossy(&b).into_owned()),
+            })
+            .collect();
+        match decl.as_str() {
+            "DATE" => parse_all(&values, parse_date)
+                .map(|days| Series::new(name.clone(), days).cast(&DataType::Date))
+                .transpose()?,
+            d if d.contains("TIMESTAMP") || d.contains("DATETIME") => {
+                parse_all(&values, parse_datetime)
+                    .map(|ms| {
+                        Series::new(name.clone(), ms)
+                            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+                    })
+                    .transpose()?
+            }
+            _ => None,
+        }
+        // Keep text that doesn't parse as declared.
+        .unwrap_or_else(|| Series::new(name, values))
+    };
+    Ok(series.into())
+}
+
+fn read_query(conn: &Connection, sql: &str) -> anyhow::Result<DataFrame> {
+    let mut stmt = conn
+        .prepare(sql)
+        .with_context(|| format!("Invalid query: {}", sql))?;
+    let columns: Vec<(String, Option<String>)> = stmt
+        .columns()
+        .iter()
+        .map(|c| (c.name().to_owned(), c.decl_type().map(str::to_owned)))
+        .collect();
+    let mut values: Vec<Vec<Value>> = vec![Vec::new(); columns.len()];
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        for (index, column) in values.iter_mut().enumerate() {
+            column.push(row.get(index)?);
+        }
+    }
+    let columns = columns
+        .iter()
+        .zip(values)
+        .map(|((name, decl), values)| column(name, decl.as_deref(), values))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(DataFrame::new(columns)?)
+}
+
+/// The declared SQLite type for a polars type.
+fn sql_type(dtype: &DataType) -> &'static str {
+    match dtype {
+        DataType::Boolean => "BOOLEAN",
+        DataType::Float32 | DataType::Float64 => "REAL",
+        dtype if dtype.is_integer() => "INTEGER",
+        DataType::Decimal(_, _) => "NUMERIC",
+        DataType::Binary => "BLOB",
+        DataType::Date => "DATE",
+        DataType::Datetime(_, _) => "TIMESTAMP",
+        _ => "TEXT",
+    }
+}
+
+/// Converts a column to the values bound to insert statements. Temporal
+/// columns become ISO 8601 text, which SQLite's date functions understand.
+fn sql_values(column: &Column) -> anyhow::Result<Vec<Value>> {
+    let series = column.as_materialized_series();
+    Ok(match series.dtype() {
+        DataType::Boolean => series
+            .bool()?
+            .into_iter()
+            .map(|v| v.map_or(Value::Null, |b| Value::Integer(i64::from(b))))
+            .collect(),
+        dtype if dtype.is_integer() => series
+            .cast(&DataType::Int64)?
+            .i64()?
+            .into_iter()
+            .map(|v| v.map_or(Value::Null, Value::Integer))
+            .collect(),
+        dtype if dtype.is_numeric() => series
+            .cast(&DataType::Float64)?
+            .f64()?
+            .into_iter()
+            .map(|v| v.map_or(Value::Null, Value::Real))
+            .collect(),
+        DataType::Binary => series
+            .binary()?
+            .into_iter()
+            .map(|v| v.map_or(Value::Null, |b| Value::Blob(b.to_vec())))
+            .collect(),
+        _ => series
+            .cast(&DataType::String)?
+            .str()?
+            .into_iter()
+            .map(|v| v.map_or(Value::Null, |s| Value::Text(s.to_owned())))
+            .collect(),
+    })
+}
+
+fn write_table(conn: &mut Connection, df: &DataFrame, args: &Args) -> anyhow::Result<()> {
+    let table = args.string("table").unwrap_or_else(|| "data".to_owned());
+    let if_exists = args
+        .string("if-exists")
+        .unwrap_or_else(|| "fail".to_owned());
+    let batch_size = match args.int("batch-size")? {
+        Some(n) if n < 1 => bail!("batch-size must be positive, got: {}", n),
+        n => n.unwrap_or(1000) as usize,
+    };
+    if df.width() == 0 {
+        bail!("Can't write a table without columns");
+    }
+
+    let tx = conn.transaction()?;
+    let exists = tx
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [&table],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    let create = match (exists, if_exists.as_str()) {
+        (false, _) => true,
+        (true, "replace") => {
+            tx.execute(&format!("DROP TABLE {}", quote(&table)), [])?;
+            true
+        }
+        (true, "append") => false,
+        (true, _) => bail!(
+            "Table {} already exists; pass if-exists=replace or if-exists=append",
+            table
+        ),
+    };
+    if create {
+        let columns: Vec<String> = df
+            .get_columns()
+            .iter()
+            .map(|c| format!("{} {}", quote(c.name()), sql_type(c.dtype())))
+            .collect();
+        let sql = format!("CREATE TABLE {} ({})", quote(&table), columns.join(", "));
+        args.log(&sql);
+        tx.execute(&sql, [])?;
+    }
+
+    let names: Vec<String> = df.get_columns().iter().map(|c| quote(c.name())).collect();
+    let row = format!("({})", vec!["?"; df.width()].join(", "));
+    let batch_size = batch_size.min(MAX_VARIABLES / df.width()).max(1);
+    for offset in (0..df.height()).step_by(batch_size) {
+        let batch = df.slice(offset as i64, batch_size);
+        let columns = batch
+            .get_columns()
+            .iter()
+            .map(sql_values)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let sql = format!(
+            "INSERT INTO {} ({}) VALUES {}",
+            quote(&table),
+            names.join(", "),
+            vec![row.as_str(); batch.height()].join(", ")
+        );
+        let params = (0..batch.height()).flat_map(|i| columns.iter().map(move |c| &c[i]));
+        tx.prepare_cached(&sql)?.execute(params_from_iter(params))?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+impl Format for SqliteFormat {
+    fn canonical_name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn aliases(&self) -> Vec<&'static str> {
+        vec!["sqlite3"]
+    }
+
+    fn extensions(&self) -> Vec<&'static str> {
+        vec!["sqlite", "sqlite3", "db"]
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        head.starts_with(MAGIC)
+    }
+
+    fn supports_nested(&self) -> bool {
+        false
+    }
+
+    fn read_options(&self) -> FormatOptions {
+        FormatOptions::new()
+            .with(FormatOption::new(
+                "table",
+                OptionKind::String,
+                "Table or view to read; needed when the database has several",
+            ))
+            .with(FormatOption::new(
+                "query",
+                OptionKind::String,
+                "SELECT query to read instead of a table",
+            ))
+    }
+
+    fn write_options(&self) -> FormatOptions {
+        FormatOptions::new()
+            .with(
+                FormatOption::new("table", OptionKind::String, "Table to write")
+                    .with_default("data"),
+            )
+            .with(
+                FormatOption::new(
+                    "if-exists",
+                    OptionKind::Enum(IF_EXISTS),
+                    "What to do when the table already exists",
+                )
+                .with_default("fail"),
+            )
+            .with(
+                FormatOption::new("batch-size", OptionKind::Int, "Rows per INSERT statement")
+                    .with_default("1000"),
+            )
+    }
+
+    fn read(&self, source: &Source, args: &Args) -> anyhow::Result<LazyFrame> {
+        // In-memory data is written out, as SQLite opens databases by path.
+        let mut file = None;
+        let path = match source.path() {
+            Some(path) => path,
+            None => {
+                let temp = file.insert(NamedTempFile::new()?);
+                temp.write_all(&source.read_bytes()?)?;
+                temp.path().to_str().context("Invalid temp file path")?
+            }
+        };
+        let conn = Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("Unable to open database: {}", path))?;
+        let sql = select(&conn, args)?;
+        Ok(read_query(&conn, &sql)?.lazy())
+    }
+
+    fn write(&self, sink: &mut Sink, args: &Args, lf: LazyFrame) -> anyhow::Result<()> {
+        let df = lf.collect()?;
+        // Existing databases are added to rather than replaced.
+        sink.with_path(|path| {
+            let mut conn = Connection::open(path)
+                .with_context(|| format!("Unable to open database: {}", path))?;
+            write_table(&mut conn, &df, args)
+        })
+    }
+}
@@ -0,0 +1,165 @@
+use crate::pandata::{Args, Format, FormatOption, FormatOptions, OptionKind};
+use crate::render::{pad, text_columns, text_width};
+use crate::sink::Sink;
+use crate::source::Source;
+use anyhow::bail;
+use polars::prelude::LazyFrame;
+use std::io::Write;
+
+const ELLIPSIS: &str = "…";
+
+/// Shows control characters as escapes, so every row is one line.
+fn clean(s: &str) -> String {
+    s.replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+/// Shortens `s` to at most `max` characters, ending it with an ellipsis.
+fn truncate(s: String, max: usize) -> String {
+    if max == 0 || text_width(&s) <= max {
+        return s;
+    }
+    let mut short: String = s.chars().take(max - 1).collect();
+    short.push_str(ELLIPSIS);
+    short
+}
+
+/// A horizontal border, e.g. `├──────┼─────┤`.
+fn border(widths: &[usize], left: &str, middle: &str, right: &str) -> String {
+    let lines: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+    format!("{}{}{}", left, lines.join(middle), right)
+}
+
+fn non_negative(args: &Args, key: &str, default: usize) -> anyhow::Result<usize> {
+    match args.int(key)? {
+        Some(n) if n < 0 => bail!("{} must not be negative, got: {}", key, n),
+        n => Ok(n.map_or(default, |n| n as usize)),
+    }
+}
+
+#[derive(Default)]
+pub struct TableFormat;
+
+impl TableFormat {
+    pub fn new() -> Self {
+        TableFormat {}
+    }
+}
+
+impl Format for TableFormat {
+    fn canonical_name(&self) -> &'static str {
+        "table"
+    }
+
+    fn aliases(&self) -> Vec<&'static str> {
+        vec!["ascii"]
+    }
+
+    fn extensions(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
+    fn supports_nested(&self) -> bool {
+        false
+    }
+
+    fn can_read(&self) -> bool {
+        false
+    }
+
+    fn read_options(&self) -> FormatOptions {
+        FormatOptions::new()
+    }
+
+    fn write_options(&self) -> FormatOptions {
+        FormatOptions::new()
+            .with(
+                FormatOption::new(
+                    "max-rows",
+                    OptionKind::Int,
+                    "Rows to show, split between the first and last; 0 shows all",
+                )
+                .with_default("20"),
+            )
+            .with(
+                FormatOption::new(
+                    "max-width",
+                    OptionKind::Int,
+                    "Characters to show of each cell; 0 shows all",
+                )
+                .with_default("32"),
+            )
+            .with(
+                FormatOption::new(
+                    "null-value",
+                    OptionKind::String,
+                    "String for missing values",
+                )
+                .with_default("null"),
+            )
+    }
+
+    fn read(&self, _source: &Source, _args: &Args) -> anyhow::Result<LazyFrame> {
+        bail!("Terminal tables can't be read")
+    }
+
+    fn write(&self, sink: &mut Sink, args: &Args, lf: LazyFrame) -> anyhow::Result<()> {
+        let max_rows = non_negative(args, "max-rows", 20)?;
+        let max_width = non_negative(args, "max-width", 32)?;
+        let null = args
+            .string("null-value")
+            .unwrap_or_else(|| "null".to_owned());
+        let df = lf.collect()?;
+        let height = df.height();
+
+        // Keep the head and tail of long frames, with a row of ellipses
+        // between them.
+        let (shown, gap) = if max_rows > 0 && height > max_rows {
+            let head = max_rows.div_ceil(2);
+            let mut shown = df.head(Some(head));
+            shown.vstack_mut(&df.tail(Some(max_rows - head)))?;
+            (shown, Some(head))
+        } else {
+            (df, None)
+        };
+        let mut columns = text_columns(&shown, &null)?;
+        let mut widths = Vec::with_capacity(columns.len());
+        for column in &mut columns {
+            column.name = truncate(clean(&column.name), max_width);
+            for cell in &mut column.cells {
+                *cell = truncate(clean(cell), max_width);
+            }
+            if let Some(gap) = gap {
+                column.cells.insert(gap, ELLIPSIS.to_owned());
+            }
+            let width = column.cells.iter().map(|c| text_width(c)).max();
+            widths.push(width.unwrap_or(0).max(text_width(&column.name)));
+        }
+
+        let mut out = sink.open()?;
+        let row = |cells: Vec<String>| format!("│ {} │", cells.join(" │ "));
+        writeln!(out, "{}", border(&widths, "┌", "┬", "┐"))?;
+        let header = columns
+            .iter()
+            .zip(&widths)
+            .map(|(c, &w)| pad(&c.name, w, c.align))
+            .collect();
+        writeln!(out, "{}", row(header))?;
+        writeln!(out, "{}", border(&widths, "├", "┼", "┤"))?;
+        for i in 0..columns[0].cells.len() {
+            let cells = columns
+                .iter()
+                .zip(&widths)
+                .map(|(c, &w)| pad(&c.cells[i], w, c.align))
+                .collect();
+            writeln!(out, "{}", row(cells))?;
+        }
+        writeln!(out, "{}", border(&widths, "└", "┴", "┘"))?;
+        if gap.is_some() {
+            writeln!(out, "{} of {} rows shown", max_rows, height)?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
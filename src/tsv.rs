@@ -1,8 +1,11 @@
-use crate::pandata::{Args, Format, FormatOptions};
-use polars::io::SerReader;
-use polars::prelude::{CsvParseOptions, CsvReadOptions, CsvWriterOptions, IntoLazy, LazyFrame};
-use std::path::PathBuf;
+use crate::csv::{csv_write_options, csv_writer_options, read_delimited};
+use crate::dialect::Dialect;
+use crate::pandata::{Args, Format, FormatOption, FormatOptions, OptionKind};
+use crate::sink::Sink;
+use crate::source::Source;
+use polars::prelude::LazyFrame;
 
+#[derive(Default)]
 pub struct TsvFormat;
 
 impl TsvFormat {
@@ -16,31 +19,41 @@ impl Format for TsvFormat {
         "tsv"
     }
 
+    fn extensions(&self) -> Vec<&'static str> {
+        vec!["tsv", "tab"]
+    }
+
+    fn supports_nested(&self) -> bool {
+        false
+    }
+
     fn read_options(&self) -> FormatOptions {
-        FormatOptions::from_keys(["separator", "quote-char"])
-    }
-
-    fn read(&self, path: &str, args: &Args) -> anyhow::Result<LazyFrame> {
-        let mut parse_options = CsvParseOptions::default().with_separator(b'\t');
-        if let Some(sep) = args.char("separator") {
-            parse_options = parse_options.with_separator(sep)
-        }
-        if let Some(quote_char) = args.char("quote-char") {
-            parse_options = parse_options.with_quote_char(Some(quote_char))
-        }
-        let read_options = CsvReadOptions::default().with_parse_options(parse_options);
-        let lf = read_options
-            .try_into_reader_with_file_path(Some(PathBuf::from(path)))?
-            .finish()?
-            .lazy();
-        Ok(lf)
-    }
-
-    fn write(&self, path: &str, _args: &Args, lf: LazyFrame) -> anyhow::Result<()> {
-        let mut options = CsvWriterOptions::default();
-        options.maintain_order = true;
-        options.serialize_options.separator = b'\t';
-        lf.sink_csv(path, options)?;
-        Ok(())
+        FormatOptions::new()
+            .with(
+                FormatOption::new("separator", OptionKind::Char, "Field delimiter")
+                    .with_default("\\t"),
+            )
+            .with(
+                FormatOption::new("quote-char", OptionKind::Char, "Quote character")
+                    .with_default("\""),
+            )
+    }
+
+    fn read(&self, source: &Source, args: &Args) -> anyhow::Result<LazyFrame> {
+        let dialect = Dialect {
+            separator: args.char("separator")?.unwrap_or(b'\t'),
+            quote_char: args.char("quote-char")?.unwrap_or(b'"'),
+            has_header: true,
+        };
+        read_delimited(source, "tsv", dialect, args)
+    }
+
+    fn write_options(&self) -> FormatOptions {
+        csv_write_options("\\t")
+    }
+
+    fn write(&self, sink: &mut Sink, args: &Args, lf: LazyFrame) -> anyhow::Result<()> {
+        let options = csv_writer_options(args, b'\t')?;
+        sink.with_path(|path| Ok(lf.sink_csv(path, options)?))
     }
 }
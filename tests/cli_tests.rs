@@ -0,0 +1,272 @@
+use std::process::Command;
+
+fn pandata() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_pandata"))
+}
+
+#[test]
+fn rejects_unknown_format_with_usage_error() {
+    let output = pandata()
+        .args(["--from", "nope", "in.nope", "out.csv"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown format"), "{}", stderr);
+}
+
+#[test]
+fn requires_format_when_reading_stdin() {
+    let output = pandata()
+        .args(["-", "out.csv"])
+        .stdin(std::process::Stdio::null())
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--from"), "{}", stderr);
+}
+
+#[test]
+fn rejects_malformed_option() {
+    let output = pandata()
+        .args(["-r", "separator", "in.csv", "out.csv"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("KEY=VALUE"), "{}", stderr);
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn lists_formats_as_json() {
+    let output = pandata().args(["formats", "--json"]).output().unwrap();
+
+    assert!(output.status.success());
+    let formats: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let csv = formats
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|f| f["name"] == "csv")
+        .unwrap();
+    assert_eq!(csv["read"], true);
+    assert_eq!(csv["extensions"][0], "csv");
+    assert_eq!(csv["read_options"][0]["name"], "separator");
+}
+
+#[cfg(all(feature = "csv", feature = "json"))]
+#[test]
+fn detects_json_on_stdin() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = pandata()
+        .args(["-", "--to", "csv"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"{\"a\":1}\n{\"a\":2}\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a\n1\n2\n");
+}
+
+#[cfg(all(feature = "avro", feature = "json"))]
+#[test]
+fn prints_avro_schema() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = pandata()
+        .args(["avro-schema", "-w", "namespace=com.example"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"{\"id\":1,\"name\":\"a\"}\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    let schema: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(schema["type"], "record");
+    assert_eq!(schema["name"], "pandata");
+    assert_eq!(schema["namespace"], "com.example");
+    assert_eq!(schema["fields"][0]["name"], "id");
+    assert_eq!(schema["fields"][1]["name"], "name");
+}
+
+#[cfg(all(feature = "csv", feature = "json"))]
+#[test]
+fn reads_csv_from_a_pipe() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = pandata()
+        .args(["--from", "csv", "-", "--to", "jsonl"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"a,b\n1,x\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "{\"a\":1,\"b\":\"x\"}\n"
+    );
+}
+
+#[cfg(all(feature = "csv", feature = "parquet"))]
+#[test]
+fn reads_parquet_from_stdin() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let dir = tempfile::tempdir().unwrap();
+    let parquet = dir.path().join("data.parquet");
+    let csv = dir.path().join("data.csv");
+    std::fs::write(&csv, "a,b\n1,x\n").unwrap();
+    let status = pandata()
+        .args([csv.to_str().unwrap(), parquet.to_str().unwrap()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let mut child = pandata()
+        .args(["--from", "parquet", "-", "--to", "csv"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(&std::fs::read(&parquet).unwrap())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a,b\n1,x\n");
+}
+
+#[cfg(all(feature = "csv", feature = "parquet"))]
+#[test]
+fn writes_parquet_to_a_pipe() {
+    let dir = tempfile::tempdir().unwrap();
+    let csv = dir.path().join("data.csv");
+    std::fs::write(&csv, "a,b\n1,x\n").unwrap();
+    let output = pandata()
+        .args([csv.to_str().unwrap(), "-", "--to", "parquet"])
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(output.stdout.starts_with(b"PAR1"));
+    assert!(output.stdout.ends_with(b"PAR1"));
+}
+
+#[cfg(all(feature = "compression", feature = "csv"))]
+#[test]
+fn compresses_output_to_a_pipe() {
+    let dir = tempfile::tempdir().unwrap();
+    let csv = dir.path().join("data.csv");
+    std::fs::write(&csv, "a,b\n1,x\n").unwrap();
+    let output = pandata()
+        .args([
+            csv.to_str().unwrap(),
+            "-",
+            "--to",
+            "csv",
+            "--compression",
+            "gzip",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stdout.starts_with(&[0x1f, 0x8b]));
+
+    let gz = dir.path().join("data.csv.gz");
+    std::fs::write(&gz, &output.stdout).unwrap();
+    let output = pandata()
+        .args([gz.to_str().unwrap(), "-", "--to", "jsonl"])
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "{\"a\":1,\"b\":\"x\"}\n"
+    );
+}
+
+#[cfg(all(feature = "csv", feature = "table"))]
+#[test]
+fn prints_a_table_to_a_pipe() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = pandata()
+        .args(["--from", "csv", "-", "--to", "table", "-w", "max-rows=2"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"n,name\n1,a\n2,b\n10,c\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "┌────┬──────┐\n\
+         │  n │ name │\n\
+         ├────┼──────┤\n\
+         │  1 │ a    │\n\
+         │  … │ …    │\n\
+         │ 10 │ c    │\n\
+         └────┴──────┘\n\
+         2 of 3 rows shown\n"
+    );
+}
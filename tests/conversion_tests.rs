@@ -4,19 +4,35 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::Result;
-use pandata::{build_pandata, Args, Format};
-use polars::prelude::{Column, DataFrame, DataType, IntoLazy, NamedFrom, Series};
+use pandata::{build_pandata, Args, Compression, ConvertOptions, Format, ListMode, Sink, Source};
+use polars::prelude::{
+    lit, Column, DataFrame, DataType, IntoLazy, NamedFrom, Series, TimeUnit, NULL,
+};
 
 #[cfg(feature = "avro")]
 use pandata::AvroFormat;
 #[cfg(feature = "csv")]
 use pandata::CsvFormat;
-#[cfg(feature = "json")]
-use pandata::JsonFormat;
+#[cfg(feature = "excel")]
+use pandata::ExcelFormat;
+#[cfg(feature = "html")]
+use pandata::HtmlFormat;
+#[cfg(feature = "ipc")]
+use pandata::IpcFormat;
+#[cfg(feature = "markdown")]
+use pandata::MarkdownFormat;
 #[cfg(feature = "parquet")]
 use pandata::ParquetFormat;
+#[cfg(feature = "sql")]
+use pandata::SqlFormat;
+#[cfg(feature = "sqlite")]
+use pandata::SqliteFormat;
+#[cfg(feature = "table")]
+use pandata::TableFormat;
 #[cfg(feature = "tsv")]
 use pandata::TsvFormat;
+#[cfg(feature = "json")]
+use pandata::{JsonFormat, JsonLinesFormat};
 
 #[derive(Clone, Copy)]
 enum FormatKind {
@@ -24,12 +40,18 @@ enum FormatKind {
     Csv,
     #[cfg(feature = "json")]
     Json,
+    #[cfg(feature = "json")]
+    JsonLines,
     #[cfg(feature = "parquet")]
     Parquet,
     #[cfg(feature = "tsv")]
     Tsv,
     #[cfg(feature = "avro")]
     Avro,
+    #[cfg(feature = "ipc")]
+    Ipc,
+    #[cfg(feature = "sqlite")]
+    Sqlite,
 }
 
 impl FormatKind {
@@ -39,12 +61,18 @@ impl FormatKind {
             FormatKind::Csv => "csv",
             #[cfg(feature = "json")]
             FormatKind::Json => "json",
+            #[cfg(feature = "json")]
+            FormatKind::JsonLines => "jsonl",
             #[cfg(feature = "parquet")]
             FormatKind::Parquet => "parquet",
             #[cfg(feature = "tsv")]
             FormatKind::Tsv => "tsv",
             #[cfg(feature = "avro")]
             FormatKind::Avro => "avro",
+            #[cfg(feature = "ipc")]
+            FormatKind::Ipc => "ipc",
+            #[cfg(feature = "sqlite")]
+            FormatKind::Sqlite => "sqlite",
         }
     }
 
@@ -53,6 +81,28 @@ impl FormatKind {
     }
 }
 
+/// Every format enabled in this build.
+fn all_kinds() -> Vec<FormatKind> {
+    vec![
+        #[cfg(feature = "csv")]
+        FormatKind::Csv,
+        #[cfg(feature = "json")]
+        FormatKind::Json,
+        #[cfg(feature = "json")]
+        FormatKind::JsonLines,
+        #[cfg(feature = "parquet")]
+        FormatKind::Parquet,
+        #[cfg(feature = "tsv")]
+        FormatKind::Tsv,
+        #[cfg(feature = "avro")]
+        FormatKind::Avro,
+        #[cfg(feature = "ipc")]
+        FormatKind::Ipc,
+        #[cfg(feature = "sqlite")]
+        FormatKind::Sqlite,
+    ]
+}
+
 static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
 struct TempDir {
@@ -139,24 +189,34 @@ fn format_for(kind: FormatKind) -> Box<dyn Format> {
         FormatKind::Csv => Box::new(CsvFormat::new()),
         #[cfg(feature = "json")]
         FormatKind::Json => Box::new(JsonFormat::new()),
+        #[cfg(feature = "json")]
+        FormatKind::JsonLines => Box::new(JsonLinesFormat::new()),
         #[cfg(feature = "parquet")]
         FormatKind::Parquet => Box::new(ParquetFormat::new()),
         #[cfg(feature = "tsv")]
         FormatKind::Tsv => Box::new(TsvFormat::new()),
         #[cfg(feature = "avro")]
         FormatKind::Avro => Box::new(AvroFormat::new()),
+        #[cfg(feature = "ipc")]
+        FormatKind::Ipc => Box::new(IpcFormat::new()),
+        #[cfg(feature = "sqlite")]
+        FormatKind::Sqlite => Box::new(SqliteFormat::new()),
     }
 }
 
 fn read_frame(kind: FormatKind, path: &Path) -> Result<DataFrame> {
     let format = format_for(kind);
-    let lf = format.read(path.to_str().unwrap(), &Args::new())?;
+    let lf = format.read(&Source::from(path.to_str().unwrap()), &Args::new())?;
     Ok(lf.collect()?)
 }
 
 fn write_frame(kind: FormatKind, path: &Path, df: &DataFrame) -> Result<()> {
     let format = format_for(kind);
-    format.write(path.to_str().unwrap(), &Args::new(), df.clone().lazy())?;
+    format.write(
+        &mut Sink::from(path.to_str().unwrap()),
+        &Args::new(),
+        df.clone().lazy(),
+    )?;
     Ok(())
 }
 
@@ -284,3 +344,1410 @@ fn converts_csv_to_avro() -> Result<()> {
 fn converts_avro_to_json() -> Result<()> {
     assert_conversion(FormatKind::Avro, FormatKind::Json)
 }
+
+#[cfg(feature = "json")]
+#[test]
+fn converts_json_to_jsonl() -> Result<()> {
+    assert_conversion(FormatKind::Json, FormatKind::JsonLines)
+}
+
+#[cfg(all(feature = "json", feature = "parquet"))]
+#[test]
+fn converts_jsonl_to_parquet() -> Result<()> {
+    assert_conversion(FormatKind::JsonLines, FormatKind::Parquet)
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn writes_json_layouts() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("out.json");
+    let df = DataFrame::new(vec![Column::from(Series::new("a".into(), &[1_i64, 2]))])?;
+    let format = JsonFormat::new();
+
+    format.write(
+        &mut Sink::from(path.to_str().unwrap()),
+        &Args::new(),
+        df.clone().lazy(),
+    )?;
+    assert_eq!(fs::read_to_string(&path)?, "[{\"a\":1},{\"a\":2}]");
+
+    let pretty = Args::from_pairs(["pretty=true"])?;
+    format.write(
+        &mut Sink::from(path.to_str().unwrap()),
+        &pretty,
+        df.clone().lazy(),
+    )?;
+    assert_eq!(
+        fs::read_to_string(&path)?,
+        "[\n  {\n    \"a\": 1\n  },\n  {\n    \"a\": 2\n  }\n]\n"
+    );
+
+    // Pretty output keeps the column order rather than sorting keys.
+    let unsorted = DataFrame::new(vec![
+        Column::new("zeta".into(), [1i64]),
+        Column::new("alpha".into(), [2i64]),
+        Column::new("mid".into(), [3i64]),
+    ])?;
+    format.write(
+        &mut Sink::from(path.to_str().unwrap()),
+        &pretty,
+        unsorted.lazy(),
+    )?;
+    assert_eq!(
+        fs::read_to_string(&path)?,
+        "[\n  {\n    \"zeta\": 1,\n    \"alpha\": 2,\n    \"mid\": 3\n  }\n]\n"
+    );
+
+    let lines = Args::from_pairs(["format=lines"])?;
+    format.write(
+        &mut Sink::from(path.to_str().unwrap()),
+        &lines,
+        df.clone().lazy(),
+    )?;
+    assert_eq!(fs::read_to_string(&path)?, "{\"a\":1}\n{\"a\":2}\n");
+    let read_back = format
+        .read(&Source::from(path.to_str().unwrap()), &Args::new())?
+        .collect()?;
+    assert_frames_equal(&df, &read_back)?;
+
+    let pretty_lines = Args::from_pairs(["format=lines", "pretty=true"])?;
+    assert!(format
+        .write(
+            &mut Sink::from(path.to_str().unwrap()),
+            &pretty_lines,
+            df.lazy()
+        )
+        .is_err());
+    Ok(())
+}
+
+#[cfg(all(feature = "ipc", feature = "csv"))]
+#[test]
+fn converts_csv_to_ipc() -> Result<()> {
+    assert_conversion(FormatKind::Csv, FormatKind::Ipc)
+}
+
+#[cfg(all(feature = "ipc", feature = "parquet"))]
+#[test]
+fn converts_ipc_to_parquet() -> Result<()> {
+    assert_conversion(FormatKind::Ipc, FormatKind::Parquet)
+}
+
+#[cfg(all(feature = "sqlite", feature = "parquet"))]
+#[test]
+fn converts_parquet_to_sqlite() -> Result<()> {
+    assert_conversion(FormatKind::Parquet, FormatKind::Sqlite)
+}
+
+#[cfg(all(feature = "sqlite", feature = "csv"))]
+#[test]
+fn converts_sqlite_to_csv() -> Result<()> {
+    assert_conversion(FormatKind::Sqlite, FormatKind::Csv)
+}
+
+#[cfg(feature = "ipc")]
+#[test]
+fn round_trips_compressed_ipc_stream() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("data.arrows");
+    let df = sample_dataframe()?;
+    let format = IpcFormat::new();
+
+    let args = Args::from_pairs(["variant=stream", "compression=zstd"])?;
+    format.write_options().validate(&args)?;
+    format.write(
+        &mut Sink::from(path.to_str().unwrap()),
+        &args,
+        df.clone().lazy(),
+    )?;
+
+    let pandata = build_pandata();
+    assert_eq!(pandata.detect_format(path.to_str().unwrap())?, Some("ipc"));
+    let actual = format
+        .read(&Source::from(path.to_str().unwrap()), &Args::new())?
+        .collect()?;
+    assert_frames_equal(&df, &actual)?;
+    Ok(())
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn reads_csv_with_separator_option() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let input_path = temp_dir.path().join("input.csv");
+    let output_path = temp_dir.path().join("output.csv");
+    fs::write(&input_path, "a;b\n1;x\n2;y\n")?;
+
+    let reader_args = Args::from_pairs(["separator=;"])?;
+    let writer_args = Args::from_pairs(["separator=|"])?;
+    let pandata = build_pandata();
+    pandata.convert_with_args(
+        input_path.to_str().unwrap(),
+        output_path.to_str().unwrap(),
+        "csv",
+        "csv",
+        &reader_args,
+        &writer_args,
+    )?;
+
+    assert_eq!(fs::read_to_string(&output_path)?, "a|b\n1|x\n2|y\n");
+    Ok(())
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn rejects_unknown_format_option() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let input_path = temp_dir.path().join("input.csv");
+    let output_path = temp_dir.path().join("output.csv");
+    fs::write(&input_path, "a,b\n1,2\n")?;
+
+    let reader_args = Args::from_pairs(["delimiter=;"])?;
+    let pandata = build_pandata();
+    let err = pandata
+        .convert_with_args(
+            input_path.to_str().unwrap(),
+            output_path.to_str().unwrap(),
+            "csv",
+            "csv",
+            &reader_args,
+            &Args::new(),
+        )
+        .unwrap_err();
+
+    let message = format!("{:#}", err);
+    assert!(message.contains("delimiter"), "{}", message);
+    assert!(message.contains("quote-char, separator"), "{}", message);
+    Ok(())
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn rejects_mistyped_format_option() -> Result<()> {
+    let options = CsvFormat::new().read_options();
+
+    let err = options
+        .validate(&Args::from_pairs(["separator=;;"])?)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "separator must be a single ASCII byte, got: \";;\""
+    );
+    assert!(options
+        .validate(&Args::from_pairs(["separator=\\t"])?)
+        .is_ok());
+    Ok(())
+}
+
+#[test]
+fn parses_typed_args() -> Result<()> {
+    let args = Args::from_pairs(["flag=yes", "count=12", "cols=a,b", "cols=c", "bad=x"])?;
+
+    assert_eq!(args.bool("flag")?, Some(true));
+    assert_eq!(args.int("count")?, Some(12));
+    assert_eq!(
+        args.list("cols"),
+        Some(vec!["a".to_owned(), "b".to_owned(), "c".to_owned()])
+    );
+    assert_eq!(args.int("missing")?, None);
+    assert!(args.int("bad").is_err());
+    Ok(())
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn writes_csv_with_write_options() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let output_path = temp_dir.path().join("output.csv");
+    let df = DataFrame::new(vec![
+        Column::from(Series::new("a".into(), &[Some(1.5_f64), None])),
+        Column::from(Series::new("b".into(), &[Some("x"), Some("y")])),
+    ])?;
+
+    let args = Args::from_pairs([
+        "header=false",
+        "quote-style=always",
+        "null-value=NA",
+        "line-terminator=\\r\\n",
+        "float-precision=2",
+    ])?;
+    let format = CsvFormat::new();
+    format.write_options().validate(&args)?;
+    format.write(
+        &mut Sink::from(output_path.to_str().unwrap()),
+        &args,
+        df.lazy(),
+    )?;
+
+    assert_eq!(
+        fs::read_to_string(&output_path)?,
+        "\"1.50\",\"x\"\r\n\"NA\",\"y\"\r\n"
+    );
+    Ok(())
+}
+
+#[cfg(all(
+    feature = "csv",
+    feature = "tsv",
+    feature = "json",
+    feature = "parquet"
+))]
+#[test]
+fn resolves_aliases_and_extensions() {
+    let pandata = build_pandata();
+
+    assert_eq!(pandata.format_for_path("data.json"), Some("json"));
+    assert_eq!(pandata.format_for_path("data.jsonl"), Some("jsonl"));
+    assert_eq!(pandata.format_for_path("data.ndjson"), Some("jsonl"));
+    assert_eq!(pandata.format_for_path("dir/data.pq"), Some("parquet"));
+    assert_eq!(pandata.format_for_path("data.tab"), Some("tsv"));
+    assert_eq!(pandata.format_for_path("data.txt"), Some("csv"));
+    assert_eq!(pandata.format_for_path("DATA.CSV"), Some("csv"));
+    assert_eq!(pandata.format_for_path("data.csv.gz"), Some("csv"));
+    assert_eq!(pandata.format_for_path("data.gz"), None);
+    #[cfg(feature = "markdown")]
+    assert_eq!(pandata.format_for_path("notes.md"), Some("markdown"));
+    #[cfg(feature = "html")]
+    assert_eq!(pandata.format_for_path("page.htm"), Some("html"));
+    assert_eq!(pandata.format_for_path("data"), None);
+
+    assert_eq!(pandata.get("PQ").unwrap().canonical_name(), "parquet");
+    assert_eq!(pandata.get("tab").unwrap().canonical_name(), "tsv");
+    assert!(pandata.get("docx").is_none());
+}
+
+#[cfg(all(feature = "parquet", feature = "avro", feature = "json"))]
+#[test]
+fn detects_format_from_content() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let df = sample_dataframe()?;
+    let pandata = build_pandata();
+
+    for kind in [FormatKind::Parquet, FormatKind::Avro, FormatKind::Json] {
+        let path = temp_dir.path().join(format!("{}.bin", kind.name()));
+        write_frame(kind, &path, &df)?;
+        assert_eq!(
+            pandata.detect_format(path.to_str().unwrap())?,
+            Some(kind.name())
+        );
+    }
+
+    let gzipped = temp_dir.path().join("data.parquet");
+    fs::write(&gzipped, [0x1f, 0x8b, 0x08, 0x00])?;
+    assert!(pandata.detect_format(gzipped.to_str().unwrap()).is_err());
+    Ok(())
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn falls_back_to_extension_when_sniffing_fails() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("data.csv");
+    fs::write(&path, "a,b\n1,2\n")?;
+
+    let pandata = build_pandata();
+    assert_eq!(pandata.detect_format(path.to_str().unwrap())?, Some("csv"));
+    Ok(())
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn explicit_options_override_sniffed_dialect() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("input.csv");
+    fs::write(&path, "a|b\n1|2\n3|4\n")?;
+    let format = CsvFormat::new();
+
+    let sniffed = format
+        .read(&Source::from(path.to_str().unwrap()), &Args::new())?
+        .collect()?;
+    assert_eq!(sniffed.get_column_names(), ["a", "b"]);
+
+    let args = Args::from_pairs(["separator=,", "has-header=false"])?;
+    let explicit = format
+        .read(&Source::from(path.to_str().unwrap()), &args)?
+        .collect()?;
+    assert_eq!(explicit.shape(), (3, 1));
+    Ok(())
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn reports_detected_settings_to_the_logger() -> Result<()> {
+    use std::cell::RefCell;
+
+    thread_local! {
+        static NOTES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+    fn record(note: &str) {
+        NOTES.with(|notes| notes.borrow_mut().push(note.to_owned()));
+    }
+
+    let temp_dir = TempDir::new()?;
+    let input_path = temp_dir.path().join("input.csv");
+    fs::write(&input_path, "a;b\n1;2\n")?;
+    let pandata = build_pandata();
+    let convert = |options: &ConvertOptions| {
+        pandata.convert_with_options(
+            input_path.to_str().unwrap(),
+            &mut Sink::bytes(),
+            "csv",
+            "csv",
+            &Args::new(),
+            &Args::new(),
+            options,
+        )
+    };
+
+    convert(&ConvertOptions::default())?;
+    assert!(NOTES.with(|notes| notes.borrow().is_empty()));
+
+    convert(&ConvertOptions {
+        log: Some(record),
+        ..Default::default()
+    })?;
+    let notes = NOTES.with(|notes| notes.take());
+    assert_eq!(
+        notes,
+        ["sniffed csv dialect: separator ';', quote-char '\"', has-header true"]
+    );
+    Ok(())
+}
+
+#[cfg(all(feature = "json", feature = "csv"))]
+#[test]
+fn flattens_nested_json_for_csv() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let input_path = temp_dir.path().join("input.jsonl");
+    let output_path = temp_dir.path().join("output.csv");
+    fs::write(
+        &input_path,
+        "{\"id\":1,\"user\":{\"name\":\"a\",\"geo\":{\"lat\":1.5}},\"tags\":[\"x\",\"y\"]}\n\
+         {\"id\":2,\"user\":{\"name\":\"b\",\"geo\":{\"lat\":2.5}},\"tags\":[]}\n",
+    )?;
+    let pandata = build_pandata();
+    let convert = |options: &ConvertOptions| {
+        pandata.convert_with_options(
+            input_path.to_str().unwrap(),
+            &mut Sink::from(output_path.to_str().unwrap()),
+            "jsonl",
+            "csv",
+            &Args::new(),
+            &Args::new(),
+            options,
+        )
+    };
+
+    convert(&ConvertOptions::default())?;
+    assert_eq!(
+        fs::read_to_string(&output_path)?,
+        "id,user.name,user.geo.lat,tags\n1,a,1.5,\"[\"\"x\"\",\"\"y\"\"]\"\n2,b,2.5,[]\n"
+    );
+
+    convert(&ConvertOptions {
+        lists: ListMode::Explode,
+        separator: "_".to_owned(),
+        ..Default::default()
+    })?;
+    assert_eq!(
+        fs::read_to_string(&output_path)?,
+        "id,user_name,user_geo_lat,tags\n1,a,1.5,x\n1,a,1.5,y\n2,b,2.5,\n"
+    );
+    Ok(())
+}
+
+#[cfg(all(feature = "json", feature = "csv"))]
+#[test]
+fn flattens_lists_of_unequal_length() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let input_path = temp_dir.path().join("input.jsonl");
+    let output_path = temp_dir.path().join("output.csv");
+    fs::write(
+        &input_path,
+        "{\"id\":1,\"a\":[1,2],\"b\":[\"x\"],\"c\":[{\"z\":1,\"m\":2}]}\n",
+    )?;
+    let pandata = build_pandata();
+    let convert = |lists: ListMode| {
+        pandata.convert_with_options(
+            input_path.to_str().unwrap(),
+            &mut Sink::from(output_path.to_str().unwrap()),
+            "jsonl",
+            "csv",
+            &Args::new(),
+            &Args::new(),
+            &ConvertOptions {
+                lists,
+                ..Default::default()
+            },
+        )
+    };
+
+    convert(ListMode::Json)?;
+    assert_eq!(
+        fs::read_to_string(&output_path)?,
+        "id,a,b,c\n1,\"[1,2]\",\"[\"\"x\"\"]\",\"[{\"\"z\"\":1,\"\"m\"\":2}]\"\n"
+    );
+
+    convert(ListMode::Explode)?;
+    assert_eq!(
+        fs::read_to_string(&output_path)?,
+        "id,a,b,c.z,c.m\n1,1,x,1,2\n1,2,x,1,2\n"
+    );
+    Ok(())
+}
+
+#[cfg(all(feature = "json", feature = "csv"))]
+#[test]
+fn unflattens_dotted_columns() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let input_path = temp_dir.path().join("input.csv");
+    let output_path = temp_dir.path().join("output.jsonl");
+    fs::write(
+        &input_path,
+        "id,user.name,user.geo.lat,a,a.b\n1,x,1.5,2,3\n",
+    )?;
+
+    let pandata = build_pandata();
+    pandata.convert_with_options(
+        input_path.to_str().unwrap(),
+        &mut Sink::from(output_path.to_str().unwrap()),
+        "csv",
+        "jsonl",
+        &Args::new(),
+        &Args::new(),
+        &ConvertOptions {
+            unflatten: true,
+            ..Default::default()
+        },
+    )?;
+
+    assert_eq!(
+        fs::read_to_string(&output_path)?,
+        "{\"id\":1,\"user\":{\"name\":\"x\",\"geo\":{\"lat\":1.5}},\"a\":2,\"a.b\":3}\n"
+    );
+    Ok(())
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn writes_parquet_with_write_options() -> Result<()> {
+    use polars::io::SerReader;
+    use polars::prelude::ParquetReader;
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("out.parquet");
+    let df = sample_dataframe()?;
+    let format = ParquetFormat::new();
+
+    let args = Args::from_pairs([
+        "compression=zstd",
+        "compression-level=19",
+        "row-group-size=2",
+        "statistics=false",
+    ])?;
+    format.write_options().validate(&args)?;
+    format.write(
+        &mut Sink::from(path.to_str().unwrap()),
+        &args,
+        df.clone().lazy(),
+    )?;
+
+    let mut reader = ParquetReader::new(fs::File::open(&path)?);
+    assert_eq!(reader.get_metadata()?.row_groups.len(), 2);
+    let actual = read_frame(FormatKind::Parquet, &path)?;
+    assert_frames_equal(&df, &actual)?;
+
+    for bad in [
+        ["compression=zstd", "compression-level=30"],
+        ["compression=gzip", "compression-level=11"],
+        ["compression=snappy", "compression-level=1"],
+        ["compression=zstd", "row-group-size=0"],
+    ] {
+        let args = Args::from_pairs(bad)?;
+        assert!(
+            format
+                .write(
+                    &mut Sink::from(path.to_str().unwrap()),
+                    &args,
+                    df.clone().lazy()
+                )
+                .is_err(),
+            "{:?}",
+            bad
+        );
+    }
+    Ok(())
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn reads_parquet_row_groups_and_columns() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("data.parquet");
+    let path_str = path.to_str().unwrap();
+    let format = ParquetFormat::new();
+    format.write(
+        &mut Sink::from(path_str),
+        &Args::from_pairs(["row-group-size=1"])?,
+        sample_dataframe()?.lazy(),
+    )?;
+
+    let args = Args::from_pairs(["row-groups=0,2-3", "columns=int_col,bool_col"])?;
+    format.read_options().validate(&args)?;
+    let df = format.read(&Source::from(path_str), &args)?.collect()?;
+    assert_eq!(df.get_column_names(), ["int_col", "bool_col"]);
+    let ints: Vec<Option<i64>> = df.column("int_col")?.i64()?.into_iter().collect();
+    assert_eq!(ints, [Some(1), Some(-7), Some(42)]);
+
+    let out_of_range = Args::from_pairs(["row-groups=4"])?;
+    assert!(format.read(&Source::from(path_str), &out_of_range).is_err());
+
+    let reversed = Args::from_pairs(["row-groups=3-1"])?;
+    let error = format
+        .read(&Source::from(path_str), &reversed)
+        .err()
+        .unwrap();
+    assert_eq!(
+        error.to_string(),
+        "row-groups range must be ascending, got: 3-1"
+    );
+    Ok(())
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn reads_parquet_globs_and_hive_partitions() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let format = ParquetFormat::new();
+    for year in [2023, 2024] {
+        let dir = temp_dir.path().join(format!("year={}", year));
+        fs::create_dir(&dir)?;
+        format.write(
+            &mut Sink::from(dir.join("part.parquet").to_str().unwrap()),
+            &Args::new(),
+            sample_dataframe()?.lazy(),
+        )?;
+    }
+
+    let df = format
+        .read(
+            &Source::from(temp_dir.path().to_str().unwrap()),
+            &Args::new(),
+        )?
+        .collect()?;
+    assert_eq!(df.height(), 8);
+    assert!(df.column("year").is_ok());
+
+    let pattern = temp_dir.path().join("year=*").join("*.parquet");
+    let args = Args::from_pairs(["hive-partitioning=false"])?;
+    let df = format
+        .read(&Source::from(pattern.to_str().unwrap()), &args)?
+        .collect()?;
+    assert_eq!(df.height(), 8);
+    assert!(df.column("year").is_err());
+
+    let data = fs::read(temp_dir.path().join("year=2023").join("part.parquet"))?;
+    let error = format.read(&Source::from(data), &args).err().unwrap();
+    assert_eq!(
+        error.to_string(),
+        "hive-partitioning can only be used when reading from a path"
+    );
+    Ok(())
+}
+
+#[cfg(feature = "avro")]
+#[test]
+fn writes_avro_with_codecs_and_record_name() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let expected = sample_dataframe()?;
+    let format = AvroFormat::new();
+    for codec in ["null", "deflate", "snappy", "zstd"] {
+        let path = temp_dir.path().join(format!("{}.avro", codec));
+        let path_str = path.to_str().unwrap();
+        let args = Args::from_pairs([
+            format!("codec={}", codec),
+            "name=Event".to_owned(),
+            "namespace=com.example".to_owned(),
+        ])?;
+        format.write_options().validate(&args)?;
+        format.write(&mut Sink::from(path_str), &args, expected.clone().lazy())?;
+
+        let header = String::from_utf8_lossy(&fs::read(&path)?).into_owned();
+        let codec_name = if codec == "zstd" { "zstandard" } else { codec };
+        assert!(header.contains(codec_name), "{}", codec);
+        assert!(header.contains(r#""name":"Event""#), "{}", header);
+        assert!(
+            header.contains(r#""namespace":"com.example""#),
+            "{}",
+            header
+        );
+
+        let actual = format
+            .read(&Source::from(path_str), &Args::new())?
+            .collect()?;
+        assert_frames_equal(&expected, &actual)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "avro")]
+#[test]
+fn reads_avro_columns_and_rows() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("data.avro");
+    let path_str = path.to_str().unwrap();
+    let format = AvroFormat::new();
+    format.write(
+        &mut Sink::from(path_str),
+        &Args::new(),
+        sample_dataframe()?.lazy(),
+    )?;
+
+    let args = Args::from_pairs(["columns=bool_col,int_col", "n-rows=3"])?;
+    format.read_options().validate(&args)?;
+    let df = format.read(&Source::from(path_str), &args)?.collect()?;
+    assert_eq!(df.get_column_names(), ["bool_col", "int_col"]);
+    let ints: Vec<Option<i64>> = df.column("int_col")?.i64()?.into_iter().collect();
+    assert_eq!(ints, [Some(1), None, Some(-7)]);
+
+    let missing = Args::from_pairs(["columns=nope"])?;
+    assert!(format.read(&Source::from(path_str), &missing).is_err());
+
+    let repeated = Args::from_pairs(["columns=int_col,int_col"])?;
+    let error = format
+        .read(&Source::from(path_str), &repeated)
+        .err()
+        .unwrap();
+    assert_eq!(error.to_string(), "Column listed twice in columns: int_col");
+    Ok(())
+}
+
+#[cfg(feature = "avro")]
+#[test]
+fn reads_avro_with_reader_schema() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("data.avro");
+    let path_str = path.to_str().unwrap();
+    let format = AvroFormat::new();
+    format.write(
+        &mut Sink::from(path_str),
+        &Args::new(),
+        sample_dataframe()?.lazy(),
+    )?;
+
+    let schema = temp_dir.path().join("reader.avsc");
+    fs::write(
+        &schema,
+        r#"{
+            "type": "record",
+            "name": "pandata",
+            "fields": [
+                {"name": "id", "type": ["null", "long"], "aliases": ["int_col"]},
+                {"name": "string_col", "type": "string"},
+                {"name": "version", "type": "int", "default": 2},
+                {"name": "comment", "type": ["null", "string"], "default": null}
+            ]
+        }"#,
+    )?;
+    let args = Args::from_pairs([format!("reader-schema={}", schema.display())])?;
+    let df = format.read(&Source::from(path_str), &args)?.collect()?;
+    assert_eq!(
+        df.get_column_names(),
+        ["id", "string_col", "version", "comment"]
+    );
+    assert_eq!(df.column("version")?.dtype(), &DataType::Int32);
+    let versions: Vec<Option<i32>> = df.column("version")?.i32()?.into_iter().collect();
+    assert_eq!(versions, [Some(2); 4]);
+    assert_eq!(df.column("comment")?.null_count(), 4);
+    let ids: Vec<Option<i64>> = df.column("id")?.i64()?.into_iter().collect();
+    assert_eq!(ids, [Some(1), None, Some(-7), Some(42)]);
+
+    fs::write(
+        &schema,
+        r#"{"type": "record", "name": "pandata", "fields": [{"name": "missing", "type": "int"}]}"#,
+    )?;
+    assert!(format.read(&Source::from(path_str), &args).is_err());
+    Ok(())
+}
+
+#[cfg(feature = "avro")]
+#[test]
+fn writes_avro_with_schema() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let schema = temp_dir.path().join("payment.avsc");
+    fs::write(
+        &schema,
+        r#"{
+            "type": "record",
+            "name": "Payment",
+            "namespace": "com.example",
+            "fields": [
+                {"name": "payment_id", "type": "long", "aliases": ["id"]},
+                {"name": "amount", "type": {"type": "bytes", "logicalType": "decimal", "precision": 10, "scale": 2}},
+                {"name": "day", "type": {"type": "int", "logicalType": "date"}},
+                {"name": "at", "type": ["null", {"type": "long", "logicalType": "timestamp-micros"}]},
+                {"name": "ref", "type": {"type": "string", "logicalType": "uuid"}}
+            ]
+        }"#,
+    )?;
+    let df = DataFrame::new(vec![
+        Column::new("id".into(), [1_i32, 2]),
+        Column::new("amount".into(), [1.5_f64, 20.25]),
+        Column::new("day".into(), [19_000_i32, 19_001]).cast(&DataType::Date)?,
+        Column::new("at".into(), [Some(1_700_000_000_000_000_i64), None])
+            .cast(&DataType::Datetime(TimeUnit::Microseconds, None))?,
+        Column::new(
+            "ref".into(),
+            [
+                "0b6f5c1e-3c3a-4c1b-9f55-1b9e1a4c2d01",
+                "5d1f9a0e-8b7c-4e2a-a1d3-2c4b6e8f0a12",
+            ],
+        ),
+        Column::new("dropped".into(), ["x", "y"]),
+    ])?;
+    let args = Args::from_pairs([format!("schema={}", schema.display())])?;
+    let format = AvroFormat::new();
+    format.write_options().validate(&args)?;
+
+    let emitted: serde_json::Value =
+        serde_json::from_str(&format.schema(df.clone().lazy(), &args)?)?;
+    assert_eq!(emitted["name"], "Payment");
+    assert_eq!(emitted["fields"][1]["type"]["logicalType"], "decimal");
+    assert_eq!(emitted["fields"][4]["type"]["logicalType"], "uuid");
+
+    let path = temp_dir.path().join("payments.avro");
+    format.write(
+        &mut Sink::from(path.to_str().unwrap()),
+        &args,
+        df.clone().lazy(),
+    )?;
+    let actual = format
+        .read(&Source::from(path.to_str().unwrap()), &Args::new())?
+        .collect()?;
+    assert_eq!(
+        actual.get_column_names(),
+        ["payment_id", "amount", "day", "at", "ref"]
+    );
+    assert_eq!(actual.column("payment_id")?.dtype(), &DataType::Int64);
+    assert_eq!(
+        actual.column("amount")?.dtype(),
+        &DataType::Decimal(Some(10), Some(2))
+    );
+    assert_eq!(actual.column("day")?.dtype(), &DataType::Date);
+    assert_eq!(actual.column("at")?.null_count(), 1);
+
+    let nulls = df
+        .lazy()
+        .with_column(lit(NULL).cast(DataType::String).alias("ref"));
+    assert!(format
+        .write(&mut Sink::from(path.to_str().unwrap()), &args, nulls)
+        .is_err());
+    Ok(())
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn scans_csv_lazily() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("data.csv");
+    fs::write(&path, "a;b\n1;x\n2;y\n")?;
+    let lf = CsvFormat::new().read(&Source::from(path.to_str().unwrap()), &Args::new())?;
+    let plan = lf.describe_plan()?;
+    assert!(plan.contains("SCAN"), "{}", plan);
+    let df = lf.collect()?;
+    assert_eq!(df.get_column_names(), ["a", "b"]);
+    assert_eq!(df.height(), 2);
+    Ok(())
+}
+
+#[test]
+fn reads_bytes_and_streams() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let pandata = build_pandata();
+    for kind in all_kinds() {
+        let path = temp_dir.path().join(format!("data.{}", kind.extension()));
+        write_frame(kind, &path, &sample_dataframe()?)?;
+        let expected = read_frame(kind, &path)?;
+        let data = fs::read(&path)?;
+
+        let from_bytes = format_for(kind)
+            .read(&Source::from(data.clone()), &Args::new())?
+            .collect()?;
+        assert_frames_equal(&expected, &from_bytes)?;
+
+        let mut stream = Source::stream(std::io::Cursor::new(data));
+        let from_stream = pandata
+            .read(&mut stream, kind.name(), &Args::new())?
+            .collect()?;
+        assert_frames_equal(&expected, &from_stream)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn writes_to_memory() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let pandata = build_pandata();
+    for kind in all_kinds() {
+        let path = temp_dir.path().join(format!("data.{}", kind.extension()));
+        write_frame(kind, &path, &sample_dataframe()?)?;
+        let expected = read_frame(kind, &path)?;
+
+        let mut sink = Sink::bytes();
+        pandata.write(
+            &mut sink,
+            kind.name(),
+            &Args::new(),
+            expected.clone().lazy(),
+        )?;
+        let data = sink.into_bytes().unwrap();
+        let actual = format_for(kind)
+            .read(&Source::from(data), &Args::new())?
+            .collect()?;
+        assert_frames_equal(&expected, &actual)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn converts_in_memory() -> Result<()> {
+    let pandata = build_pandata();
+    let mut sink = Sink::bytes();
+    pandata.convert_with_options(
+        b"a,b\n1,x\n2,y\n".to_vec(),
+        &mut sink,
+        "csv",
+        "jsonl",
+        &Args::new(),
+        &Args::new(),
+        &ConvertOptions::default(),
+    )?;
+    assert_eq!(
+        sink.into_bytes().unwrap(),
+        "{\"a\":1,\"b\":\"x\"}\n{\"a\":2,\"b\":\"y\"}\n"
+    );
+    Ok(())
+}
+
+#[cfg(all(feature = "compression", feature = "csv", feature = "json"))]
+#[test]
+fn round_trips_compressed_files() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let input_path = temp_dir.path().join("input.csv");
+    fs::write(&input_path, "a,b\n1,x\n2,y\n")?;
+    let pandata = build_pandata();
+    for ext in ["gz", "zst", "bz2", "xz", "lz4"] {
+        let compressed = temp_dir.path().join(format!("data.jsonl.{}", ext));
+        let compressed = compressed.to_str().unwrap();
+        pandata.convert(input_path.to_str().unwrap(), compressed, "csv", "jsonl")?;
+        assert_ne!(fs::read(compressed)?.first(), Some(&b'{'));
+
+        let mut source = Source::from(compressed);
+        assert_eq!(
+            pandata.detect_source_format(&mut source, None)?,
+            Some("jsonl")
+        );
+        let mut sink = Sink::bytes();
+        pandata.convert_with_options(
+            source,
+            &mut sink,
+            "jsonl",
+            "csv",
+            &Args::new(),
+            &Args::new(),
+            &ConvertOptions::default(),
+        )?;
+        assert_eq!(sink.into_bytes().unwrap(), "a,b\n1,x\n2,y\n");
+    }
+    Ok(())
+}
+
+#[cfg(all(feature = "compression", feature = "csv"))]
+#[test]
+fn compresses_with_an_explicit_codec_and_level() -> Result<()> {
+    let pandata = build_pandata();
+    let options = ConvertOptions {
+        compression: Some(Compression::Zstd),
+        compression_level: Some(19),
+        ..ConvertOptions::default()
+    };
+    let mut sink = Sink::bytes();
+    pandata.convert_with_options(
+        b"a\n1\n".to_vec(),
+        &mut sink,
+        "csv",
+        "csv",
+        &Args::new(),
+        &Args::new(),
+        &options,
+    )?;
+    let data = sink.into_bytes().unwrap();
+    assert_eq!(Compression::sniff(&data), Some(Compression::Zstd));
+
+    let bad_level = ConvertOptions {
+        compression: Some(Compression::Gzip),
+        compression_level: Some(10),
+        ..ConvertOptions::default()
+    };
+    assert!(pandata
+        .convert_with_options(
+            b"a\n1\n".to_vec(),
+            &mut Sink::bytes(),
+            "csv",
+            "csv",
+            &Args::new(),
+            &Args::new(),
+            &bad_level,
+        )
+        .is_err());
+    Ok(())
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn detects_format_of_a_stream() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("data.parquet");
+    write_frame(FormatKind::Parquet, &path, &sample_dataframe()?)?;
+
+    let pandata = build_pandata();
+    let mut source = Source::stream(fs::File::open(&path)?);
+    assert_eq!(
+        pandata.detect_source_format(&mut source, None)?,
+        Some("parquet")
+    );
+    let df = pandata
+        .read(&mut source, "parquet", &Args::new())?
+        .collect()?;
+    assert_eq!(df.height(), 4);
+    Ok(())
+}
+
+#[cfg(feature = "excel")]
+fn write_workbook(path: &Path) -> Result<()> {
+    use rust_xlsxwriter::{ExcelDateTime, Format as CellFormat, Workbook};
+
+    let mut workbook = Workbook::new();
+    workbook.add_worksheet().set_name("Notes")?;
+    let sheet = workbook.add_worksheet().set_name("Data")?;
+    sheet.write_string(0, 0, "Quarterly report")?;
+    for (col, name) in ["name", "count", "price", "active", "day", "at"]
+        .into_iter()
+        .enumerate()
+    {
+        sheet.write_string(1, col as u16, name)?;
+    }
+    let date = CellFormat::new().set_num_format("yyyy-mm-dd");
+    let datetime = CellFormat::new().set_num_format("yyyy-mm-dd hh:mm");
+    for (row, (name, count, price, active)) in [("a", 1.0, 2.5, true), ("b", 3.0, 0.25, false)]
+        .into_iter()
+        .enumerate()
+    {
+        let row = row as u32 + 2;
+        sheet.write_string(row, 0, name)?;
+        sheet.write_number(row, 1, count)?;
+        sheet.write_number(row, 2, price)?;
+        sheet.write_boolean(row, 3, active)?;
+        let day = ExcelDateTime::from_ymd(2024, 1, row as u8)?;
+        sheet.write_datetime_with_format(row, 4, &day, &date)?;
+        sheet.write_datetime_with_format(row, 5, &day.clone().and_hms(12, 30, 0)?, &datetime)?;
+    }
+    workbook.save(path)?;
+    Ok(())
+}
+
+#[cfg(feature = "excel")]
+#[test]
+fn reads_typed_excel_cells() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("report.xlsx");
+    write_workbook(&path)?;
+
+    let pandata = build_pandata();
+    let mut source = Source::from(path.to_str().unwrap());
+    assert_eq!(
+        pandata.detect_source_format(&mut source, None)?,
+        Some("excel")
+    );
+
+    let args = Args::from_pairs(["sheet=Data", "header-row=1"])?;
+    let df = pandata.read(&mut source, "excel", &args)?.collect()?;
+    assert_eq!(
+        df.get_column_names(),
+        ["name", "count", "price", "active", "day", "at"]
+    );
+    let dtypes: Vec<DataType> = df.dtypes();
+    assert_eq!(
+        dtypes,
+        [
+            DataType::String,
+            DataType::Int64,
+            DataType::Float64,
+            DataType::Boolean,
+            DataType::Date,
+            DataType::Datetime(TimeUnit::Milliseconds, None),
+        ]
+    );
+    let days = df.column("day")?.cast(&DataType::String)?;
+    assert_eq!(days.str()?.get(1), Some("2024-01-03"));
+    let at = df.column("at")?.cast(&DataType::String)?;
+    assert_eq!(at.str()?.get(0), Some("2024-01-02 12:30:00.000"));
+    Ok(())
+}
+
+#[cfg(feature = "excel")]
+#[test]
+fn reads_excel_sheet_by_index_and_range() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("report.xlsx");
+    write_workbook(&path)?;
+
+    let format = ExcelFormat::new();
+    let args = Args::from_pairs(["sheet=1", "range=B2:C3"])?;
+    format.read_options().validate(&args)?;
+    let df = format
+        .read(&Source::from(path.to_str().unwrap()), &args)?
+        .collect()?;
+    assert_eq!(df.get_column_names(), ["count", "price"]);
+    assert_eq!(df.height(), 1);
+    assert_eq!(df.column("price")?.f64()?.get(0), Some(2.5));
+
+    let no_header = Args::from_pairs(["sheet=Data", "range=A3:A4", "has-header=false"])?;
+    let df = format
+        .read(&Source::from(fs::read(&path)?), &no_header)?
+        .collect()?;
+    assert_eq!(df.get_column_names(), ["column_1"]);
+    assert_eq!(df.height(), 2);
+
+    let missing = Args::from_pairs(["sheet=Totals"])?;
+    assert!(format
+        .read(&Source::from(path.to_str().unwrap()), &missing)
+        .is_err());
+
+    for (opts, message) in [
+        (
+            ["sheet=Data", "range=AAAAAAAAAAAAZ1"],
+            "Invalid cell reference",
+        ),
+        (["sheet=Data", "range=XFE1"], "Invalid cell reference"),
+        (
+            ["sheet=Data", "header-row=10"],
+            "header-row 10 is beyond the",
+        ),
+    ] {
+        let error = format
+            .read(
+                &Source::from(path.to_str().unwrap()),
+                &Args::from_pairs(opts)?,
+            )
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains(message), "{}", error);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "excel")]
+#[test]
+fn writes_typed_excel_sheets() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("out.xlsx");
+    let path_str = path.to_str().unwrap();
+    let df = DataFrame::new(vec![
+        Column::new("name".into(), ["a", "b", "c"]),
+        Column::new("count".into(), [Some(1i64), None, Some(3)]),
+        Column::new("price".into(), [0.5, 1.25, 2.0]),
+        Column::new("active".into(), [true, false, true]),
+        Column::new("day".into(), [19_724i32, 19_725, 19_726]).cast(&DataType::Date)?,
+        Column::new("at".into(), [1_704_198_600_000i64, 0, 90_000_000])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?,
+    ])?;
+
+    let format = ExcelFormat::new();
+    let args = Args::from_pairs(["sheet=Data", "rows-per-sheet=2"])?;
+    format.write_options().validate(&args)?;
+    assert!(format
+        .write(&mut Sink::from(path_str), &args, df.clone().lazy())
+        .is_err());
+
+    let args = Args::from_pairs(["sheet=Data", "rows-per-sheet=2", "split-sheets=true"])?;
+    format.write(&mut Sink::from(path_str), &args, df.clone().lazy())?;
+    let first = format
+        .read(&Source::from(path_str), &Args::from_pairs(["sheet=Data"])?)?
+        .collect()?;
+    let second = format
+        .read(
+            &Source::from(path_str),
+            &Args::from_pairs(["sheet=Data_2"])?,
+        )?
+        .collect()?;
+    assert_frames_equal(&df.slice(0, 2), &first)?;
+    assert_frames_equal(&df.slice(2, 1), &second)?;
+
+    assert!(format
+        .write(
+            &mut Sink::from(temp_dir.path().join("out.ods").to_str().unwrap()),
+            &Args::new(),
+            df.lazy()
+        )
+        .is_err());
+    Ok(())
+}
+
+#[cfg(all(feature = "excel", feature = "json"))]
+#[test]
+fn flattens_nested_json_for_excel() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let input_path = temp_dir.path().join("nested.jsonl");
+    let output_path = temp_dir.path().join("out.xlsx");
+    fs::write(
+        &input_path,
+        "{\"id\":1,\"user\":{\"name\":\"a\"},\"tags\":[1,2]}\n\
+         {\"id\":2,\"user\":{\"name\":\"b\"},\"tags\":[]}\n",
+    )?;
+    build_pandata().convert(
+        input_path.to_str().unwrap(),
+        output_path.to_str().unwrap(),
+        "jsonl",
+        "excel",
+    )?;
+
+    let df = ExcelFormat::new()
+        .read(&Source::from(output_path.to_str().unwrap()), &Args::new())?
+        .collect()?;
+    assert_eq!(df.get_column_names(), ["id", "user.name", "tags"]);
+    let names: Vec<Option<&str>> = df.column("user.name")?.str()?.into_iter().collect();
+    assert_eq!(names, [Some("a"), Some("b")]);
+    let tags: Vec<Option<&str>> = df.column("tags")?.str()?.into_iter().collect();
+    assert_eq!(tags, [Some("[1,2]"), Some("[]")]);
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn writes_and_queries_sqlite_tables() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("data.db");
+    let path_str = path.to_str().unwrap();
+    let df = DataFrame::new(vec![
+        Column::new("id".into(), [1i64, 2, 3]),
+        Column::new("day".into(), [19_724i32, 19_725, 19_726]).cast(&DataType::Date)?,
+        Column::new("at".into(), [Some(1_704_198_600_000i64), None, Some(0)])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?,
+    ])?;
+
+    let format = SqliteFormat::new();
+    let args = Args::from_pairs(["table=events", "batch-size=2"])?;
+    format.write_options().validate(&args)?;
+    format.write(&mut Sink::from(path_str), &args, df.clone().lazy())?;
+    assert!(format
+        .write(&mut Sink::from(path_str), &args, df.clone().lazy())
+        .is_err());
+
+    let append = Args::from_pairs(["table=events", "if-exists=append"])?;
+    format.write(&mut Sink::from(path_str), &append, df.clone().lazy())?;
+    let read = Args::from_pairs(["table=events"])?;
+    let events = format.read(&Source::from(path_str), &read)?.collect()?;
+    assert_frames_equal(&df.vstack(&df)?, &events)?;
+
+    let replace = Args::from_pairs(["table=events", "if-exists=replace"])?;
+    format.write(&mut Sink::from(path_str), &replace, df.clone().lazy())?;
+    let events = format.read(&Source::from(path_str), &read)?.collect()?;
+    assert_frames_equal(&df, &events)?;
+
+    let query = Args::from_pairs(["query=SELECT id * 10 AS id FROM events WHERE id > 1"])?;
+    let ids = format.read(&Source::from(path_str), &query)?.collect()?;
+    let ids: Vec<Option<i64>> = ids.column("id")?.i64()?.into_iter().collect();
+    assert_eq!(ids, [Some(20), Some(30)]);
+
+    format.write(
+        &mut Sink::from(path_str),
+        &Args::from_pairs(["table=other"])?,
+        df.lazy(),
+    )?;
+    assert!(format.read(&Source::from(path_str), &Args::new()).is_err());
+    Ok(())
+}
+
+#[cfg(all(feature = "sqlite", feature = "json"))]
+#[test]
+fn flattens_nested_json_for_sqlite() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let input_path = temp_dir.path().join("nested.jsonl");
+    let output_path = temp_dir.path().join("out.sqlite");
+    fs::write(
+        &input_path,
+        "{\"id\":1,\"user\":{\"name\":\"a\"},\"tags\":[1,2]}\n\
+         {\"id\":2,\"user\":{\"name\":\"b\"},\"tags\":[]}\n",
+    )?;
+    build_pandata().convert(
+        input_path.to_str().unwrap(),
+        output_path.to_str().unwrap(),
+        "jsonl",
+        "sqlite",
+    )?;
+
+    let conn = rusqlite::Connection::open(&output_path)?;
+    let columns: Vec<(String, String)> = conn
+        .prepare("SELECT name, type FROM pragma_table_info('data')")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    assert_eq!(
+        columns,
+        [
+            ("id".to_owned(), "INTEGER".to_owned()),
+            ("user.name".to_owned(), "TEXT".to_owned()),
+            ("tags".to_owned(), "TEXT".to_owned()),
+        ]
+    );
+    let tags: Vec<String> = conn
+        .prepare("SELECT tags FROM data ORDER BY id")?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    assert_eq!(tags, ["[1,2]", "[]"]);
+    Ok(())
+}
+
+/// Writes `df` to memory as text.
+#[cfg(any(
+    feature = "sql",
+    feature = "markdown",
+    feature = "html",
+    feature = "table"
+))]
+fn render(format: &dyn Format, df: &DataFrame, opts: &[&str]) -> Result<String> {
+    let mut sink = Sink::bytes();
+    format.write(
+        &mut sink,
+        &Args::from_pairs(opts.iter().copied())?,
+        df.clone().lazy(),
+    )?;
+    Ok(String::from_utf8(sink.into_bytes().unwrap().to_vec())?)
+}
+
+#[cfg(feature = "sql")]
+#[test]
+fn writes_sql_scripts_per_dialect() -> Result<()> {
+    let df = DataFrame::new(vec![
+        Column::new("id".into(), [1i64, 2]),
+        Column::new("name".into(), [Some("O'Brien \\ co"), None]),
+        Column::new("ok".into(), [true, false]),
+        Column::new("at".into(), [Some(1_704_198_600_000i64), None])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))?,
+    ])?;
+
+    assert_eq!(
+        render(&SqlFormat::new(), &df, &["dialect=postgres", "table=people", "batch-size=1"])?,
+        "CREATE TABLE \"people\" (\n  \"id\" BIGINT,\n  \"name\" TEXT,\n  \"ok\" BOOLEAN,\n  \"at\" TIMESTAMP\n);\n\
+         INSERT INTO \"people\" (\"id\", \"name\", \"ok\", \"at\") VALUES\n  \
+         (1, 'O''Brien \\ co', TRUE, TIMESTAMP '2024-01-02 12:30:00.000');\n\
+         INSERT INTO \"people\" (\"id\", \"name\", \"ok\", \"at\") VALUES\n  \
+         (2, NULL, FALSE, NULL);\n"
+    );
+
+    let mysql = render(
+        &SqlFormat::new(),
+        &df,
+        &["dialect=mysql", "create-table=false"],
+    )?;
+    assert_eq!(
+        mysql,
+        "INSERT INTO `data` (`id`, `name`, `ok`, `at`) VALUES\n  \
+         (1, 'O''Brien \\\\ co', TRUE, '2024-01-02 12:30:00.000'),\n  \
+         (2, NULL, FALSE, NULL);\n"
+    );
+
+    let floats = DataFrame::new(vec![Column::new("x".into(), [1.5, f64::NAN])])?;
+    assert!(render(&SqlFormat::new(), &floats, &["dialect=ansi"]).is_err());
+    assert!(
+        render(&SqlFormat::new(), &floats, &["dialect=postgres"])?.contains("(1.5),\n  ('NaN');")
+    );
+    Ok(())
+}
+
+#[cfg(all(feature = "sql", feature = "sqlite"))]
+#[test]
+fn sqlite_scripts_load_into_sqlite() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("seed.db");
+    let path_str = path.to_str().unwrap();
+    let df = DataFrame::new(vec![
+        Column::new("id".into(), [1i64, 2, 3]),
+        Column::new("name".into(), [Some("a'b"), None, Some("c")]),
+        Column::new("ok".into(), [Some(true), Some(false), None]),
+        Column::new("day".into(), [19_724i32, 19_725, 19_726]).cast(&DataType::Date)?,
+    ])?;
+
+    let script = render(&SqlFormat::new(), &df, &["dialect=sqlite", "batch-size=2"])?;
+    rusqlite::Connection::open(&path)?.execute_batch(&script)?;
+    let read = SqliteFormat::new()
+        .read(&Source::from(path_str), &Args::new())?
+        .collect()?;
+    assert_frames_equal(&df, &read)?;
+    Ok(())
+}
+
+#[cfg(any(feature = "markdown", feature = "html", feature = "table"))]
+fn render_sample() -> Result<DataFrame> {
+    Ok(DataFrame::new(vec![
+        Column::new("id".into(), [1i64, 20]),
+        Column::new("name".into(), [Some("a|<b>"), None]),
+        Column::new("ok".into(), [true, false]),
+    ])?)
+}
+
+#[cfg(feature = "markdown")]
+#[test]
+fn writes_markdown_tables() -> Result<()> {
+    let markdown = render(&MarkdownFormat::new(), &render_sample()?, &["null-value=-"])?;
+    assert_eq!(
+        markdown,
+        "|  id | name   |  ok   |\n\
+         | --: | ------ | :---: |\n\
+         |   1 | a\\|<b> | true  |\n\
+         |  20 | -      | false |\n"
+    );
+    Ok(())
+}
+
+#[cfg(feature = "html")]
+#[test]
+fn writes_html_tables() -> Result<()> {
+    let df = render_sample()?;
+    let html = render(&HtmlFormat::new(), &df, &["class=data wide"])?;
+    assert!(
+        html.starts_with("<table class=\"data wide\">\n"),
+        "{}",
+        html
+    );
+    assert!(html.contains(
+        "<tr><td style=\"text-align: right\">1</td><td>a|&lt;b&gt;</td>\
+         <td style=\"text-align: center\">true</td></tr>"
+    ));
+    assert!(html.ends_with("</table>\n"));
+
+    let page = render(&HtmlFormat::new(), &df, &["standalone=true", "title=A & B"])?;
+    assert!(page.starts_with("<!DOCTYPE html>\n"));
+    assert!(page.contains("<title>A &amp; B</title>"));
+    assert!(page.ends_with("</table>\n</body>\n</html>\n"));
+    Ok(())
+}
+
+#[cfg(feature = "table")]
+#[test]
+fn writes_terminal_tables() -> Result<()> {
+    let df = render_sample()?;
+    assert_eq!(
+        render(&TableFormat::new(), &df, &["max-width=4"])?,
+        "┌────┬──────┬──────┐\n\
+         │ id │ name │  ok  │\n\
+         ├────┼──────┼──────┤\n\
+         │  1 │ a|<… │ true │\n\
+         │ 20 │ null │ fal… │\n\
+         └────┴──────┴──────┘\n"
+    );
+    assert!(render(&TableFormat::new(), &df, &["max-rows=-1"]).is_err());
+    Ok(())
+}
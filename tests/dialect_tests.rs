@@ -0,0 +1,40 @@
+#![cfg(feature = "csv")]
+
+use pandata::Dialect;
+
+#[test]
+fn sniffs_separator() {
+    for (sample, separator) in [
+        ("a,b,c\n1,2,3\n4,5,6\n", b','),
+        ("a;b;c\n1,5;2;3\n4;5;6\n", b';'),
+        ("a|b\nx, y|1\nz|2\n", b'|'),
+        ("a\tb\n1\t2\n", b'\t'),
+    ] {
+        assert_eq!(
+            Dialect::sniff(sample.as_bytes()).separator,
+            separator,
+            "{:?}",
+            sample
+        );
+    }
+}
+
+#[test]
+fn ignores_separators_inside_quotes() {
+    let dialect = Dialect::sniff(b"name;note\n'x';'a, b, c'\n'y';'d, e'\n");
+    assert_eq!(dialect.separator, b';');
+    assert_eq!(dialect.quote_char, b'\'');
+}
+
+#[test]
+fn sniffs_header() {
+    assert!(Dialect::sniff(b"id,name\n1,ann\n2,bob\n").has_header);
+    assert!(!Dialect::sniff(b"1,ann\n2,bob\n3,cy\n").has_header);
+    assert!(!Dialect::sniff(b"AB,x\nCD,y\nEF,z\n").has_header);
+    assert!(Dialect::sniff(b"code,x\nCD,y\nEF,z\n").has_header);
+}
+
+#[test]
+fn defaults_without_a_separator() {
+    assert_eq!(Dialect::sniff(b"value\n1\n2\n"), Dialect::default());
+}